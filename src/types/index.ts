@@ -7,6 +7,9 @@ export interface SnapResult {
     x: number;
     y: number;
     guides: Array<{ orientation: string; pos: number; start: number; end: number; guide_type: string }>;
+    /** rects index the x / y alignment snapped to (absent when that axis didn't align) */
+    matched_x_index?: number;
+    matched_y_index?: number;
 }
 
 // ─── SIDEBAR PANEL ────────────────────────────────────────────────────────────
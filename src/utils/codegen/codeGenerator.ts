@@ -16,13 +16,22 @@ export interface GridLayout {
   // Optional — absent when using an older WASM build without v0.2 output.
   frColumns?: string;            // e.g. "0.08fr 0.53fr 0.33fr"
   frRows?: string;               // e.g. "0.06fr 0.47fr 0.47fr"
+  gap?: string;                  // e.g. "16px 24px" — present when uniform gutters were collapsed
   items: Array<{
     id: string;
     colStart: number;            // 1-based CSS line number (inclusive start)
     colEnd: number;              // 1-based CSS line number (exclusive end)
     rowStart: number;
     rowEnd: number;
+    zIndex?: number;             // set only on items that share cells with another item
+    area?: string;               // grid-area name when gridTemplateAreas is present
   }>;
+  overlaps?: Array<[string, string]>; // id pairs whose cells intersect (stacked layout)
+  gridTemplateAreas?: string;    // e.g. '"header header" "side main"' (namedAreas option)
+  origin?: [number, number];     // canvas position of line 1 (x, y), used by update_grid_item
+  emptyCells?: Array<[number, number]>; // 1-based (col, row) cells no item covers
+  options?: Record<string, unknown>; // GridOptions the layout was built with (update_grid_item)
+  canvasWidth?: number;          // canvas width the fr fallbacks were computed against
 }
 
 class ImportManager {
@@ -669,6 +678,9 @@ export const generateGridPage = (
     ? `To lock to pixel sizes, switch off fr units:\n *   "${gridLayout.templateColumns}"`
     : `To make this fluid, convert to fr units:\n *   "${buildFrTemplateStrings(gridLayout.colWidthsPx, gridLayout.rowHeightsPx, canvasWidth, canvasHeight).templateColumns}"`;
 
+  // collapsed uniform gutters come back as a CSS gap
+  const gapLine = gridLayout.gap ? `        gap: '${gridLayout.gap}',\n` : '';
+
   return `${importBlock}${metadataBlock}
 /**
  * ${componentName} — Responsive CSS Grid Layout
@@ -686,7 +698,7 @@ export default function ${componentName}() {
         display: 'grid',
         gridTemplateColumns: '${templateColumns}',
         gridTemplateRows: '${templateRows}',
-        width: ${canvasWidth},
+${gapLine}        width: ${canvasWidth},
         minHeight: ${canvasHeight},
         margin: '0 auto',
         position: 'relative',
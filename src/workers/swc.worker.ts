@@ -5,7 +5,8 @@
 //     Used by RightSidebar Code tab for live feedback while the user types.
 //   • COMPILE_MINIFIED message: full compile with minification (~35% smaller).
 //     Used by Header ZIP export for smaller bundled output files.
-//   • Parse errors now include line:col from SWC span data.
+//   • Parse errors arrive as JSON { message, line, column, snippet } and are
+//     forwarded as `diagnostic` alongside the error component.
 
 import init, { SwcCompiler } from '../../vectra-engine/pkg/vectra_engine.js';
 
@@ -53,6 +54,22 @@ function makeErrorComponent(msg: string): string {
     ].join('\n');
 }
 
+/** Parse failures arrive as JSON `{ message, line, column, snippet }`. */
+interface CompileDiagnostic { message: string; line: number; column: number; snippet: string; }
+
+function parseDiagnostic(err: unknown): CompileDiagnostic | null {
+    try {
+        const d = JSON.parse(err instanceof Error ? err.message : String(err));
+        return d && typeof d.line === 'number' ? d as CompileDiagnostic : null;
+    } catch { return null; }
+}
+
+function errorMessage(err: unknown): string {
+    const d = parseDiagnostic(err);
+    if (d) return `${d.line}:${d.column} — ${d.message}`;
+    return err instanceof Error ? err.message : String(err);
+}
+
 // ── Message handler ───────────────────────────────────────────────────────────
 self.onmessage = (e: MessageEvent) => {
     const { id, code, messageType } = e.data as {
@@ -101,8 +118,7 @@ self.onmessage = (e: MessageEvent) => {
             const transpiled = shimExports(compiler.compile_minified(clean));
             self.postMessage({ id, code: transpiled });
         } catch (err: unknown) {
-            const msg = err instanceof Error ? err.message : String(err);
-            self.postMessage({ id, code: makeErrorComponent(msg) });
+            self.postMessage({ id, code: makeErrorComponent(errorMessage(err)), diagnostic: parseDiagnostic(err) });
         }
         return;
     }
@@ -113,7 +129,6 @@ self.onmessage = (e: MessageEvent) => {
         const transpiled = shimExports(compiler.compile(clean));
         self.postMessage({ id, code: transpiled });
     } catch (err: unknown) {
-        const msg = err instanceof Error ? err.message : String(err);
-        self.postMessage({ id, code: makeErrorComponent(msg) });
+        self.postMessage({ id, code: makeErrorComponent(errorMessage(err)), diagnostic: parseDiagnostic(err) });
     }
 };
\ No newline at end of file
@@ -82,10 +82,10 @@ pub fn repair_json(json_str: String) -> String {
                 let value_start = start + open_q + 1;
                 let bytes = s.as_bytes();
                 let mut in_str = true; let mut escaped = false;
-                for i in value_start..bytes.len() {
+                for &b in &bytes[value_start..] {
                     if escaped { escaped = false; continue; }
-                    if bytes[i] == b'\\' { escaped = true; continue; }
-                    if bytes[i] == b'"' { in_str = false; break; }
+                    if b == b'\\' { escaped = true; continue; }
+                    if b == b'"' { in_str = false; break; }
                 }
                 if in_str { s.push_str("</div>}\""); }
             }
@@ -5,6 +5,15 @@
 //  §7  generate_react_code — node-tree → React JSX exporter
 //      Walks the VectraProject flat map from a root ID and emits JSX.
 //      Used by codeGenerator.ts generateCode() fast-path.
+//      generate_react_code_with — same, with ExportOptions → { code, warnings }.
+//      isComponent nodes are extracted into named function components;
+//      propName text nodes become `props.<name>` (typed with `typescript`).
+//      generate_pages — one component file per `type: "page"` node.
+//      validate_icons — pre-flight split of used icon names into known/unknown.
+//      validate_project — dangling child refs, cycles and orphans from a root.
+//      generate_vue_code — same walk → Vue 3 SFC (<script setup> + <template>).
+//      generate_html_code — same walk → static HTML with a <style> block.
+//      generate_svelte_code — same walk → Svelte component (<script> + markup).
 //
 //  §17 collect_stack_on_mobile_ids — tree walk for stackOnMobile:true nodes
 //      Mirrors collectStackOnMobileIds() in codeGenerator.ts
@@ -12,11 +21,15 @@
 //  §17 slug_to_next_path — URL slug → Next.js App Router file path
 //      "/" → "app/page.tsx", "/about" → "app/about/page.tsx"
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write as FmtWrite;
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use crate::styling::{camel_to_kebab, logical_class, logical_style, style_decls};
+use crate::state::fnv1a;
+use crate::thumbnail::px_val;
+use crate::layout::is_hidden;
 
 // ── §7 Types ──────────────────────────────────────────────────────────────────
 
@@ -28,39 +41,678 @@ pub struct VectraNode {
     pub other:    HashMap<String, Value>,
 }
 
+/// Default nesting limit — far deeper than any real design, well inside the wasm stack.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Export options; `""` / missing keys → defaults.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    /// Nodes nested deeper than this are replaced by a placeholder comment.
+    pub max_depth:  usize,
+    pub style_mode: StyleMode,
+    /// Append `Component.displayName = "<page/node name>"` for React devtools.
+    pub display_name: bool,
+    pub root_size: RootSize,
+    /// Prefix generated class names with `vx-<hash4>-` (hash of the component
+    /// name) so exported stylesheets don't collide inside a larger app.
+    pub scope_classes: bool,
+    /// Hoist style colors into a `theme` object served by a generated
+    /// `ThemeProvider`; nodes reference `theme.colors.<name>` inline.
+    pub theme: bool,
+    /// `dir="rtl"` on the root and logical properties/utilities instead of
+    /// physical left/right ones.
+    pub rtl: bool,
+    /// Indentation unit: a space count (`2`, `4`) or `"tab"`. Default two spaces.
+    #[serde(deserialize_with = "indent_unit")]
+    pub indent: String,
+    /// Emit `props.role` / `props.ariaLabel`, an `aria-label` derived from the
+    /// icon on icon-only buttons, and `alt=""` on images without one. On by default.
+    pub a11y: bool,
+    /// Emit image nodes as `<Image>` from `next/image` (imported when used).
+    /// Next requires `width` / `height`: they come from the node's px
+    /// `style.width` / `style.height` (else `props.width` / `props.height`);
+    /// an image with neither gets `fill` and a warning, since its parent then
+    /// needs `position: relative` and a size.
+    pub next_image: bool,
+    /// `.tsx` output: every function returns `React.JSX.Element`, props are
+    /// typed by a `<Name>Props` interface (prop-driven `propName` text), the
+    /// theme provider's props and generated key handlers are annotated, and
+    /// type-only imports use `import type`.
+    pub typescript: bool,
+}
+
+fn indent_unit<'de, D: serde::Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    match Value::deserialize(d)? {
+        Value::Number(n) => n.as_u64().filter(|&n| n <= 16).map(|n| " ".repeat(n as usize))
+            .ok_or_else(|| serde::de::Error::custom("indent: expected 0..=16 spaces")),
+        Value::String(s) if s == "tab" || s == "\t" => Ok("\t".into()),
+        Value::String(s) if s.chars().all(|c| c == ' ') => Ok(s),
+        v => Err(serde::de::Error::custom(format!("indent: expected a space count or \"tab\", got {}", v))),
+    }
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            max_depth: DEFAULT_MAX_DEPTH, style_mode: StyleMode::default(),
+            display_name: false, root_size: RootSize::default(), scope_classes: false,
+            theme: false, rtl: false, indent: "  ".into(), a11y: true, next_image: false,
+            typescript: false,
+        }
+    }
+}
+
+/// Outer dimensions on the root element so it doesn't collapse in a flexible parent.
+/// `fixed` — the frame's px width/height; `responsive` — `width: '100%'` plus
+/// the frame height as `minHeight`.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RootSize { #[default] None, Fixed, Responsive }
+
+/// Where `props.style` goes; `className` is emitted in every mode.
+/// `inline` (default) — `props.style` as an inline `style={{…}}`.
+/// `tailwind` — className only; style objects are skipped, on the
+/// assumption the utilities already carry the design.
+/// `cssModule` — `props.style` becomes stylesheet rules; nodes with identical
+/// declarations share one class.
+/// Theme tokens and the root size are inline `style` entries in all modes,
+/// and win over same-named `props.style` keys.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StyleMode { #[default] Inline, Tailwind, CssModule }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub code:      String,
+    pub warnings:  Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub css:       Option<String>,
+    /// Node id → stylesheet class (cssModule mode only).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub class_map: BTreeMap<String, String>,
+    /// Token name → value (theme mode only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme:     Option<BTreeMap<String, String>>,
+}
+
+/// Per-export mutable state threaded through the tree walk.
+struct GenCtx<'a> {
+    p:         &'a HashMap<String, VectraNode>,
+    root_id:   &'a str,
+    opts:      &'a ExportOptions,
+    warnings:  Vec<String>,
+    /// Declaration block → shared class, plus rules in first-use order.
+    rule_ids:  HashMap<String, String>,
+    rules:     Vec<(String, String)>,
+    class_map: BTreeMap<String, String>,
+    /// "" or `vx-<hash4>-` when `scope_classes` is on.
+    class_prefix: String,
+    /// Color value → token name (theme mode only).
+    theme:     HashMap<String, String>,
+    /// Ids on the current render path — a child already here is a cycle.
+    on_path:   HashSet<String>,
+    /// An `<Image>` was emitted, so `next/image` must be imported.
+    next_image: bool,
+    /// Extracted `isComponent` subtrees, first-use order.
+    components: Vec<ComponentDef>,
+    /// `propName`s read by the function being generated, first-use order.
+    props:     Vec<String>,
+    /// The default export's name — subcomponents must not reuse it.
+    export_name: String,
+}
+
+struct ComponentDef { name: String, jsx: String, props: Vec<String> }
+
+impl GenCtx<'_> {
+    /// Shared class for a node's `props.style`, allocating a rule on first use.
+    fn style_class(&mut self, id: &str, style: &serde_json::Map<String, Value>) -> Option<String> {
+        let mut decls = style_decls(style);
+        if decls.is_empty() { return None; }
+        decls.sort();
+        let block = decls.join(";\n  ");
+        let class = match self.rule_ids.get(&block) {
+            Some(c) => c.clone(),
+            None => {
+                let c = format!("{}node-{}", self.class_prefix, self.rules.len() + 1);
+                self.rule_ids.insert(block.clone(), c.clone());
+                self.rules.push((c.clone(), block));
+                c
+            }
+        };
+        self.class_map.insert(id.to_string(), class.clone());
+        Some(class)
+    }
+
+    fn stylesheet(&self) -> String {
+        self.rules.iter().map(|(c, b)| format!(".{} {{\n  {};\n}}\n", c, b)).collect::<Vec<_>>().join("\n")
+    }
+}
+
 // ── §7 generate_react_code ────────────────────────────────────────────────────
 
 #[wasm_bindgen]
 pub fn generate_react_code(project_val: JsValue, root_id: String) -> Result<String, JsValue> {
     let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
-    let mut export_root = root_id.clone();
-    if let Some(n) = project.get(&root_id) {
-        if n.other.get("type").and_then(|v| v.as_str()) == Some("page") {
-            if let Some(c) = &n.children { if !c.is_empty() { export_root = c[0].clone(); } }
-        }
+    Ok(react_code(&project, &root_id))
+}
+
+/// `generate_react_code` with options (JSON string, `""` → defaults).
+/// Returns `{ code, warnings }`; warnings report truncated subtrees.
+#[wasm_bindgen]
+pub fn generate_react_code_with(project_val: JsValue, root_id: String, options_json: String) -> Result<JsValue, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    let opts: ExportOptions = if options_json.trim().is_empty() { ExportOptions::default() } else {
+        serde_json::from_str(&options_json).map_err(|e| JsValue::from_str(&format!("[codegen] options parse: {}", e)))?
+    };
+    Ok(export_react(&project, &root_id, &opts).serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Export every `type == "page"` node as its own component file.
+/// Returns `{ [pageName]: code }`; each file imports only the icons it uses.
+/// Duplicate page names get a numeric suffix ("Home", "Home 2").
+#[wasm_bindgen]
+pub fn generate_pages(project_val: JsValue) -> Result<JsValue, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    let files = pages_code(&project);
+    Ok(files.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+pub(crate) fn pages_code(project: &HashMap<String, VectraNode>) -> BTreeMap<String, String> {
+    let mut page_ids: Vec<&String> = project.iter()
+        .filter(|(_, n)| n.other.get("type").and_then(|v| v.as_str()) == Some("page"))
+        .map(|(id, _)| id).collect();
+    page_ids.sort();
+    let mut files = BTreeMap::new();
+    for id in page_ids {
+        let base = project[id].other.get("name").and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty()).unwrap_or("Page").to_string();
+        let mut name = base.clone(); let mut n = 2;
+        while files.contains_key(&name) { name = format!("{} {}", base, n); n += 1; }
+        files.insert(name, react_code(project, id));
     }
-    let mut icons = HashSet::new();
-    collect_icons(&project, &export_root, &mut icons);
+    files
+}
+
+pub(crate) fn react_code(project: &HashMap<String, VectraNode>, root_id: &str) -> String {
+    export_react(project, root_id, &ExportOptions::default()).code
+}
+
+/// A `page` exports its visible children (usually one frame); anything else
+/// — or a page with none — exports itself. Every exporter skips hidden
+/// nodes, so they neither render nor pull in icons / theme colors.
+fn export_roots_of(project: &HashMap<String, VectraNode>, root_id: &str) -> Vec<String> {
+    project.get(root_id)
+        .filter(|n| n.other.get("type").and_then(|v| v.as_str()) == Some("page"))
+        .and_then(|n| n.children.clone())
+        .map(|c| c.into_iter().filter(|id| project.get(id).is_some_and(|n| !is_hidden(n))).collect::<Vec<_>>())
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| vec![root_id.to_string()])
+}
+
+pub(crate) fn export_react(project: &HashMap<String, VectraNode>, root_id: &str, opts: &ExportOptions) -> ExportResult {
+    let roots = export_roots_of(project, root_id);
+    // Several page children render side by side in a fragment; the component
+    // is then named after the page rather than its first child.
+    let export_root = if roots.len() == 1 { roots[0].clone() } else { root_id.to_string() };
+    let (mut icons, mut seen) = (HashSet::new(), HashSet::new());
+    for r in &roots { collect_icons(project, r, &mut icons, &mut seen, opts.max_depth); }
+
+    let tokens = if opts.theme { theme_tokens(project, &roots, opts.max_depth) } else { Vec::new() };
 
     let mut code = String::new();
-    code.push_str("import React from 'react';\n");
+    code.push_str(if opts.theme { "import React, { createContext, useContext } from 'react';\n" } else { "import React from 'react';\n" });
+    if opts.theme && opts.typescript { code.push_str("import type { ReactNode } from 'react';\n"); }
     if !icons.is_empty() {
         let mut list: Vec<&String> = icons.iter().collect(); list.sort();
         let _ = writeln!(code, "import {{ {} }} from 'lucide-react';",
             list.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", "));
     }
-    let name = project.get(&export_root)
+    let image_import_at = code.len();
+    let u = opts.indent.as_str();
+    if opts.theme { push_theme_module(&mut code, &tokens, u, opts.typescript); }
+    let raw_name = [root_id, export_root.as_str()].iter()
+        .find_map(|id| project.get(*id).and_then(|n| n.other.get("name")).and_then(|v| v.as_str()))
+        .filter(|s| !s.trim().is_empty());
+    let mut name = project.get(&export_root)
         .and_then(|n| n.other.get("name").and_then(|v| v.as_str()))
         .unwrap_or("MyComponent")
         .replace(|c: char| !c.is_alphanumeric(), "");
-    let _ = writeln!(code, "\nexport default function {}() {{\n  return (", name);
-    gen_node_rec(&project, &export_root, &mut code, 2, None);
-    code.push_str("  );\n}\n");
-    Ok(code)
+    if !name.starts_with(|c: char| c.is_alphabetic()) { name = format!("Component{}", name); }
+    let mut cx = GenCtx {
+        p: project, root_id, opts, warnings: Vec::new(),
+        rule_ids: HashMap::new(), rules: Vec::new(), class_map: BTreeMap::new(),
+        class_prefix: if opts.scope_classes { scope_prefix(&name) } else { String::new() },
+        theme: tokens.iter().map(|(k, v)| (v.clone(), k.clone())).collect(),
+        on_path: HashSet::new(), next_image: false,
+        components: Vec::new(), props: Vec::new(), export_name: name.clone(),
+    };
+    let mut jsx = String::new();
+    if roots.len() == 1 {
+        gen_node_rec(&mut cx, &roots[0], &mut jsx, 2, 0, None);
+    } else {
+        let _ = writeln!(jsx, "{u}{u}<>");
+        for r in &roots { gen_node_rec(&mut cx, r, &mut jsx, 3, 0, None); }
+        let _ = writeln!(jsx, "{u}{u}</>");
+    }
+    let hook = if opts.theme { format!("{u}const theme = useTheme();\n") } else { String::new() };
+    let ret = if opts.typescript { ": React.JSX.Element" } else { "" };
+    for c in &cx.components {
+        let param = push_props_interface(&mut code, &c.name, &c.props, opts);
+        let _ = write!(code, "\nfunction {}({}){} {{\n{}{u}return (\n{}{u});\n}}\n", c.name, param, ret, hook, c.jsx);
+    }
+    let param = push_props_interface(&mut code, &name, &cx.props, opts);
+    if opts.theme {
+        let _ = writeln!(code, "\nfunction {}Content({}){} {{\n{}{u}return (\n{}{u});\n}}", name, param, ret, hook, jsx);
+    } else {
+        let _ = writeln!(code, "\nexport default function {}({}){} {{\n{u}return (\n{}{u});\n}}", name, param, ret, jsx);
+    }
+    if cx.next_image { code.insert_str(image_import_at, "import Image from 'next/image';\n"); }
+    if opts.theme {
+        let spread = if cx.props.is_empty() { "" } else { " {...props}" };
+        let _ = writeln!(code, "\nexport default function {0}({1}){3} {{\n{u}return (\n{u}{u}<ThemeProvider>\n{u}{u}{u}<{0}Content{2} />\n{u}{u}</ThemeProvider>\n{u});\n}}", name, param, spread, ret);
+    }
+    if opts.display_name {
+        let label = raw_name.unwrap_or(name.as_str());
+        let _ = writeln!(code, "\n{}.displayName = {};", name, Value::from(label));
+    }
+    let css = (opts.style_mode == StyleMode::CssModule).then(|| cx.stylesheet());
+    let theme = opts.theme.then(|| tokens.into_iter().collect());
+    ExportResult { code, warnings: cx.warnings, css, class_map: cx.class_map, theme }
+}
+
+// ── §7 generate_vue_code ──────────────────────────────────────────────────────
+
+/// Vue 3 single-file component: `<script setup>` importing the used icons
+/// from lucide-vue-next, and a `<template>` built by the same walk as the
+/// React exporter. Hidden nodes are left out.
+#[wasm_bindgen]
+pub fn generate_vue_code(project_val: JsValue, root_id: String) -> Result<String, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(vue_code(&project, &root_id))
+}
+
+pub(crate) fn vue_code(project: &HashMap<String, VectraNode>, root_id: &str) -> String {
+    let roots = export_roots_of(project, root_id);
+    let (mut icons, mut seen) = (HashSet::new(), HashSet::new());
+    for r in &roots { collect_icons(project, r, &mut icons, &mut seen, DEFAULT_MAX_DEPTH); }
+    let mut code = String::from("<script setup>\n");
+    if !icons.is_empty() {
+        let mut list: Vec<&str> = icons.iter().map(|s| s.as_str()).collect(); list.sort();
+        let _ = writeln!(code, "import {{ {} }} from 'lucide-vue-next';", list.join(", "));
+    }
+    code.push_str("</script>\n\n<template>\n");
+    for r in &roots { markup_node_rec(project, r, &mut code, 1, 0, Markup::Vue, &mut HashSet::new()); }
+    code.push_str("</template>\n");
+    code
+}
+
+/// Template flavour for the shared Vue / Svelte walk.
+#[derive(Clone, Copy, PartialEq)]
+enum Markup { Vue, Svelte }
+
+/// `path` holds the ids being rendered above `id`; see `gen_node_rec`.
+fn markup_node_rec(p: &HashMap<String, VectraNode>, id: &str, buf: &mut String, indent: usize, depth: usize, m: Markup, path: &mut HashSet<String>) {
+    if !path.insert(id.to_string()) {
+        let _ = writeln!(buf, "{}<!-- cycle: \"{}\" skipped -->", "  ".repeat(indent), id);
+        return;
+    }
+    markup_node_body(p, id, buf, indent, depth, m, path);
+    path.remove(id);
+}
+
+fn markup_node_body(p: &HashMap<String, VectraNode>, id: &str, buf: &mut String, indent: usize, depth: usize, m: Markup, path: &mut HashSet<String>) {
+    let Some(n) = p.get(id) else { return };
+    if is_hidden(n) { return; }
+    let sp = "  ".repeat(indent);
+    if depth >= DEFAULT_MAX_DEPTH {
+        let _ = writeln!(buf, "{}<!-- truncated: max depth {} exceeded -->", sp, DEFAULT_MAX_DEPTH);
+        return;
+    }
+    let nt    = n.other.get("type").and_then(|v| v.as_str()).unwrap_or("div");
+    let props = n.other.get("props");
+    let content = n.other.get("content").and_then(|v| v.as_str()).unwrap_or("");
+    let mut classes: Vec<String> = props.and_then(|p| p.get("className")).and_then(|v| v.as_str())
+        .map(|c| c.split_whitespace().map(String::from).collect()).unwrap_or_default();
+    if let Some(t) = props.and_then(|p| p.get("transition")) { transition_classes(t, &mut classes); }
+    if nt == "image" { object_fit_classes(props, &mut classes); }
+    let mut ps = if classes.is_empty() { String::new() } else { format!(" class=\"{}\"", markup_attr(&classes.join(" "), m)) };
+    // onMouseEnter → `@mouseenter="h"` (Vue) / `on:mouseenter={h}` (Svelte).
+    for (k, h) in event_handlers(n) {
+        let ev = k[2..].to_ascii_lowercase();
+        let _ = match m { Markup::Vue => write!(ps, " @{}=\"{}\"", ev, h), Markup::Svelte => write!(ps, " on:{}={{{}}}", ev, h) };
+    }
+    if nt == "icon" {
+        if let Some(name) = props.and_then(|p| p.get("iconName")).and_then(|v| v.as_str()) {
+            let _ = writeln!(buf, "{}<{}{} />", sp, name, ps);
+            return;
+        }
+    }
+    let tag = html_tag(nt, props);
+    if tag == "img" {
+        if let Some(src) = image_src(n) { let _ = write!(ps, " src=\"{}\"", markup_attr(src, m)); }
+        if let Some(alt) = prop_str(props, "alt") { let _ = write!(ps, " alt=\"{}\"", markup_attr(alt, m)); }
+    }
+    if matches!(tag, "img"|"input") { let _ = writeln!(buf, "{}<{}{} />", sp, tag, ps); return; }
+    // Svelte reads `{…}` as an expression, so such text goes in as a string literal.
+    let mut cb = match m { Markup::Svelte => jsx_text(content), Markup::Vue => vue_text(content) };
+    if let Some(ch) = &n.children {
+        let list = matches!(tag, "ul"|"ol");
+        for c in ch.iter().filter(|c| p.get(*c).is_some_and(|n| !is_hidden(n))) {
+            if list {
+                let lsp = "  ".repeat(indent + 1);
+                let mut item = String::new();
+                markup_node_rec(p, c, &mut item, indent + 2, depth + 1, m, path);
+                let _ = write!(cb, "{}<li>\n{}{}</li>\n", lsp, item, lsp);
+            } else {
+                markup_node_rec(p, c, &mut cb, indent + 1, depth + 1, m, path);
+            }
+        }
+    }
+    // Svelte warns on self-closing non-void HTML tags; Vue templates allow them.
+    if cb.is_empty() && m == Markup::Vue { let _ = writeln!(buf, "{}<{}{} />", sp, tag, ps); }
+    else if cb.contains('\n') { let _ = writeln!(buf, "{}<{}{}>\n{}{}</{}>", sp, tag, ps, cb, sp, tag); }
+    else { let _ = writeln!(buf, "{}<{}{}>{}</{}>", sp, tag, ps, cb, tag); }
+}
+
+// ── §7 generate_svelte_code ───────────────────────────────────────────────────
+
+/// Svelte component: a `<script>` importing the used icons from
+/// lucide-svelte, then markup from the same walk as the Vue exporter —
+/// `class=`, `on:event={handler}`, text with braces/angle brackets as
+/// `{"…"}` string expressions, and attribute braces as `&#123;` / `&#125;`.
+/// Hidden nodes are left out.
+#[wasm_bindgen]
+pub fn generate_svelte_code(project_val: JsValue, root_id: String) -> Result<String, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(svelte_code(&project, &root_id))
+}
+
+pub(crate) fn svelte_code(project: &HashMap<String, VectraNode>, root_id: &str) -> String {
+    let roots = export_roots_of(project, root_id);
+    let (mut icons, mut seen) = (HashSet::new(), HashSet::new());
+    for r in &roots { collect_icons(project, r, &mut icons, &mut seen, DEFAULT_MAX_DEPTH); }
+    let mut code = String::from("<script>\n");
+    if !icons.is_empty() {
+        let mut list: Vec<&str> = icons.iter().map(|s| s.as_str()).collect(); list.sort();
+        let _ = writeln!(code, "  import {{ {} }} from 'lucide-svelte';", list.join(", "));
+    }
+    code.push_str("</script>\n\n");
+    for r in &roots { markup_node_rec(project, r, &mut code, 0, 0, Markup::Svelte, &mut HashSet::new()); }
+    code
+}
+
+// ── §7 generate_html_code ─────────────────────────────────────────────────────
+
+/// Static HTML document. `props.style` objects become rules in a `<style>`
+/// block (identical declarations share one `node-N` class, as in cssModule
+/// mode); `className` passes through as `class`. Icons have no runtime to
+/// render them, so they are emitted as `<span data-icon="Name" aria-hidden="true">`
+/// placeholders for a script or CSS mask to fill in. Hidden nodes are left out.
+#[wasm_bindgen]
+pub fn generate_html_code(project_val: JsValue, root_id: String) -> Result<String, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(html_code(&project, &root_id))
+}
+
+pub(crate) fn html_code(project: &HashMap<String, VectraNode>, root_id: &str) -> String {
+    let roots = export_roots_of(project, root_id);
+    let opts = ExportOptions { style_mode: StyleMode::CssModule, ..ExportOptions::default() };
+    let mut cx = GenCtx {
+        p: project, root_id, opts: &opts, warnings: Vec::new(),
+        rule_ids: HashMap::new(), rules: Vec::new(), class_map: BTreeMap::new(),
+        class_prefix: String::new(), theme: HashMap::new(), on_path: HashSet::new(), next_image: false,
+        components: Vec::new(), props: Vec::new(), export_name: String::new(),
+    };
+    let mut body = String::new();
+    for r in &roots { html_node_rec(&mut cx, r, &mut body, 1, 0); }
+    let title = project.get(root_id).and_then(|n| n.other.get("name")).and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty()).unwrap_or("Export");
+    let mut code = format!("<!DOCTYPE html>\n<html>\n<head>\n  <meta charset=\"utf-8\">\n  <title>{}</title>\n", html_escape(title));
+    if !cx.rules.is_empty() { let _ = write!(code, "  <style>\n{}  </style>\n", cx.stylesheet()); }
+    let _ = write!(code, "</head>\n<body>\n{}</body>\n</html>\n", body);
+    code
+}
+
+fn html_node_rec(cx: &mut GenCtx, id: &str, buf: &mut String, indent: usize, depth: usize) {
+    if !cx.on_path.insert(id.to_string()) {
+        let _ = writeln!(buf, "{}<!-- cycle: \"{}\" skipped -->", "  ".repeat(indent), id);
+        return;
+    }
+    html_node_body(cx, id, buf, indent, depth);
+    cx.on_path.remove(id);
+}
+
+fn html_node_body(cx: &mut GenCtx, id: &str, buf: &mut String, indent: usize, depth: usize) {
+    let Some(n) = cx.p.get(id) else { return };
+    if is_hidden(n) { return; }
+    let sp = "  ".repeat(indent);
+    if depth >= cx.opts.max_depth {
+        let _ = writeln!(buf, "{}<!-- truncated: max depth {} exceeded -->", sp, cx.opts.max_depth);
+        return;
+    }
+    let nt    = n.other.get("type").and_then(|v| v.as_str()).unwrap_or("div");
+    let props = n.other.get("props");
+    let content = n.other.get("content").and_then(|v| v.as_str()).unwrap_or("");
+    let mut classes: Vec<String> = props.and_then(|p| p.get("className")).and_then(|v| v.as_str())
+        .map(|c| c.split_whitespace().map(String::from).collect()).unwrap_or_default();
+    if let Some(c) = props.and_then(|p| p.get("style")).and_then(|v| v.as_object()).and_then(|st| cx.style_class(id, st)) {
+        classes.insert(0, c);
+    }
+    let mut ps = if classes.is_empty() { String::new() } else { format!(" class=\"{}\"", html_escape(&classes.join(" "))) };
+    if nt == "icon" {
+        let name = props.and_then(|p| p.get("iconName")).and_then(|v| v.as_str()).unwrap_or("HelpCircle");
+        let _ = writeln!(buf, "{}<span{} data-icon=\"{}\" aria-hidden=\"true\"></span>", sp, ps, html_escape(name));
+        return;
+    }
+    let tag = html_tag(nt, props);
+    if tag == "img" {
+        if let Some(src) = image_src(n) { let _ = write!(ps, " src=\"{}\"", html_escape(src)); }
+        let alt = prop_str(props, "alt").unwrap_or("");
+        let _ = write!(ps, " alt=\"{}\"", html_escape(alt));
+    }
+    if matches!(tag, "img"|"input") { let _ = writeln!(buf, "{}<{}{}>", sp, tag, ps); return; }
+    let mut cb = html_escape(content);
+    if let Some(ch) = &n.children {
+        let list = matches!(tag, "ul"|"ol");
+        for c in ch.iter().filter(|c| cx.p.get(*c).is_some_and(|n| !is_hidden(n))) {
+            if list {
+                let lsp = "  ".repeat(indent + 1);
+                let mut item = String::new();
+                html_node_rec(cx, c, &mut item, indent + 2, depth + 1);
+                let _ = write!(cb, "{}<li>\n{}{}</li>\n", lsp, item, lsp);
+            } else {
+                html_node_rec(cx, c, &mut cb, indent + 1, depth + 1);
+            }
+        }
+    }
+    if cb.contains('\n') { let _ = writeln!(buf, "{}<{}{}>\n{}{}</{}>", sp, tag, ps, cb, sp, tag); }
+    else { let _ = writeln!(buf, "{}<{}{}>{}</{}>", sp, tag, ps, cb, tag); }
+}
+
+/// Escape text / attribute values for HTML output.
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Vue template text: HTML-escaped, and each `{{` emitted as the literal
+/// interpolation `{{ '{{' }}` so designer text never becomes an expression.
+fn vue_text(s: &str) -> String {
+    html_escape(s).replace("{{", "{{ '{{' }}")
+}
+
+/// Quoted attribute value for the Vue / Svelte walk. Svelte evaluates `{…}`
+/// inside attribute values too, so braces become `&#123;` / `&#125;` there.
+fn markup_attr(s: &str, m: Markup) -> String {
+    let s = html_escape(s);
+    match m { Markup::Svelte => s.replace('{', "&#123;").replace('}', "&#125;"), Markup::Vue => s }
+}
+
+// ── §7 Theme tokens ───────────────────────────────────────────────────────────
+
+/// Color literal in a style value (hex / rgb[a] / hsl[a]), normalized for grouping.
+fn color_literal(v: &Value) -> Option<String> {
+    let s = v.as_str()?.trim();
+    let l = s.to_ascii_lowercase();
+    (l.starts_with('#') || l.starts_with("rgb") || l.starts_with("hsl")).then(|| l.replace(' ', ""))
+}
+
+fn count_colors<'a>(p: &'a HashMap<String, VectraNode>, id: &'a str, counts: &mut HashMap<String, usize>, seen: &mut HashSet<&'a str>, depth_left: usize) {
+    let Some(n) = p.get(id) else { return };
+    if depth_left == 0 || is_hidden(n) || !seen.insert(id) { return; }
+    if let Some(st) = n.other.get("props").and_then(|p| p.get("style")).and_then(|v| v.as_object()) {
+        for c in st.values().filter_map(color_literal) { *counts.entry(c).or_default() += 1; }
+    }
+    if let Some(ch) = &n.children { for c in ch { count_colors(p, c, counts, seen, depth_left - 1); } }
+}
+
+/// Distinct style colors under `roots` as `(token, value)`, most used first:
+/// the top one is `brand`, the rest `color2`, `color3`, ….
+fn theme_tokens(p: &HashMap<String, VectraNode>, roots: &[String], max_depth: usize) -> Vec<(String, String)> {
+    let mut counts = HashMap::new();
+    let mut seen = HashSet::new();
+    for r in roots { count_colors(p, r, &mut counts, &mut seen, max_depth); }
+    let mut by_use: Vec<(String, usize)> = counts.into_iter().collect();
+    by_use.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    by_use.into_iter().enumerate()
+        .map(|(i, (v, _))| (if i == 0 { "brand".into() } else { format!("color{}", i + 1) }, v))
+        .collect()
+}
+
+fn push_theme_module(code: &mut String, tokens: &[(String, String)], u: &str, ts: bool) {
+    let _ = writeln!(code, "\nexport const theme = {{\n{u}colors: {{");
+    for (k, v) in tokens { let _ = writeln!(code, "{u}{u}{}: {},", k, Value::from(v.as_str())); }
+    let _ = write!(code, "{u}}},\n}};\n\n");
+    code.push_str("const ThemeContext = createContext(theme);\n\n");
+    code.push_str(if ts {
+        "export function ThemeProvider({ value = theme, children }: { value?: typeof theme; children?: ReactNode }): React.JSX.Element {\n"
+    } else { "export function ThemeProvider({ value = theme, children }) {\n" });
+    let _ = write!(code, "{u}return <ThemeContext.Provider value={{value}}>{{children}}</ThemeContext.Provider>;\n}}\n\n");
+    code.push_str("export const useTheme = () => useContext(ThemeContext);\n");
+}
+
+// ── §7 validate_icons ─────────────────────────────────────────────────────────
+
+/// Mirrors `ICON_NAMES` in src/data/iconRegistry.ts — sorted for binary search.
+const KNOWN_ICONS: &[&str] = &[
+    "Activity", "Airplay", "AlertCircle", "AlertTriangle", "AlignCenter", "AlignJustify",
+    "AlignLeft", "AlignRight", "Anchor", "Aperture", "Archive", "ArrowDown", "ArrowLeft",
+    "ArrowRight", "ArrowUp", "AtSign", "Award", "BarChart", "BarChart2", "Battery",
+    "BatteryCharging", "Bell", "BellOff", "Bluetooth", "Bold", "Book", "BookOpen", "Bookmark",
+    "Box", "Briefcase", "Calendar", "Camera", "Cast", "Check", "CheckCircle", "CheckSquare",
+    "ChevronDown", "ChevronLeft", "ChevronRight", "ChevronUp", "ChevronsDown", "ChevronsUp",
+    "Chrome", "Circle", "Clipboard", "Clock", "Cloud", "CloudDrizzle", "CloudLightning", "CloudOff",
+    "CloudRain", "CloudSnow", "Code", "Codepen", "Codesandbox", "Coffee", "Columns", "Command",
+    "Compass", "Copy", "CornerDownLeft", "CornerDownRight", "Cpu", "CreditCard", "Crop",
+    "Crosshair", "Database", "Delete", "Disc", "DollarSign", "Download", "DownloadCloud", "Droplet",
+    "Edit", "Edit2", "Edit3", "ExternalLink", "Eye", "EyeOff", "Facebook", "FastForward", "Feather",
+    "Figma", "File", "FileMinus", "FilePlus", "FileText", "Film", "Filter", "Flag", "Folder",
+    "FolderMinus", "FolderPlus", "Framer", "Frown", "Gift", "GitBranch", "GitCommit", "GitMerge",
+    "GitPullRequest", "Github", "Globe", "Grid", "HardDrive", "Hash", "Headphones", "Heart",
+    "HelpCircle", "Hexagon", "Home", "Image", "Inbox", "Info", "Instagram", "Italic", "Key",
+    "Layers", "Layout", "LifeBuoy", "Link", "Link2", "Linkedin", "List", "Loader", "Lock", "LogIn",
+    "LogOut", "Mail", "Map", "MapPin", "Maximize", "Maximize2", "Meh", "Menu", "MessageCircle",
+    "MessageSquare", "Mic", "MicOff", "Minimize", "Minimize2", "Minus", "MinusCircle",
+    "MinusSquare", "Monitor", "Moon", "MoreHorizontal", "MoreVertical", "MousePointer", "Move",
+    "Music", "Navigation", "Navigation2", "Octagon", "Package", "Paperclip", "Pause", "PauseCircle",
+    "PenTool", "Percent", "Phone", "PhoneCall", "PhoneForwarded", "PhoneIncoming", "PhoneMissed",
+    "PhoneOff", "PhoneOutgoing", "PieChart", "Play", "PlayCircle", "Plus", "PlusCircle",
+    "PlusSquare", "Pocket", "Power", "Printer", "Radio", "RefreshCcw", "RefreshCw", "Repeat",
+    "Rewind", "RotateCcw", "RotateCw", "Rss", "Save", "Scissors", "Search", "Send", "Server",
+    "Settings", "Share", "Share2", "Shield", "ShieldOff", "ShoppingBag", "ShoppingCart", "Shuffle",
+    "Sidebar", "SkipBack", "SkipForward", "Slack", "Slash", "Sliders", "Smartphone", "Smile",
+    "Speaker", "Square", "Star", "StopCircle", "Sun", "Sunrise", "Sunset", "Tablet", "Tag",
+    "Target", "Terminal", "Thermometer", "ThumbsDown", "ThumbsUp", "ToggleLeft", "ToggleRight",
+    "Tool", "Trash", "Trash2", "Trello", "TrendingDown", "TrendingUp", "Triangle", "Truck", "Tv",
+    "Twitch", "Twitter", "Type", "Umbrella", "Underline", "Unlock", "Upload", "UploadCloud", "User",
+    "UserCheck", "UserMinus", "UserPlus", "UserX", "Users", "Video", "VideoOff", "Voicemail",
+    "Volume", "Volume1", "Volume2", "VolumeX", "Watch", "Wifi", "WifiOff", "Wind", "X", "XCircle",
+    "XOctagon", "XSquare", "Youtube", "Zap", "ZapOff", "ZoomIn", "ZoomOut",
+];
+
+#[derive(Serialize)]
+pub struct IconReport {
+    pub valid:   Vec<String>,
+    pub invalid: Vec<String>,
+}
+
+/// Pre-flight check: icon names used under `root_id`, split into known
+/// registry names and unknown ones (which render as HelpCircle). Read-only.
+#[wasm_bindgen]
+pub fn validate_icons(project_val: JsValue, root_id: String) -> Result<JsValue, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(serde_wasm_bindgen::to_value(&icon_report(&project, &root_id))?)
+}
+
+pub(crate) fn icon_report(project: &HashMap<String, VectraNode>, root_id: &str) -> IconReport {
+    let (mut icons, mut seen) = (HashSet::new(), HashSet::new());
+    collect_icons(project, root_id, &mut icons, &mut seen, DEFAULT_MAX_DEPTH);
+    let mut used: Vec<String> = icons.into_iter().collect(); used.sort();
+    let (valid, invalid) = used.into_iter().partition(|n| KNOWN_ICONS.binary_search(&n.as_str()).is_ok());
+    IconReport { valid, invalid }
+}
+
+/// One structural problem found by `validate_project`. `kind` is
+/// `missing-root`, `dangling-child` (`child` names the missing id), `cycle`
+/// (`child` is an ancestor of `node` listed again as its child) or `orphan`
+/// (unreachable from the root).
+#[derive(Serialize)]
+pub struct ProjectIssue {
+    pub kind:  &'static str,
+    pub node:  String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub child: Option<String>,
+}
+
+/// Pre-flight structural check before export: walks from `root_id` and lists
+/// dangling child refs, cycles and orphan nodes. Clean project → `[]`.
+#[wasm_bindgen]
+pub fn validate_project(project_val: JsValue, root_id: String) -> Result<JsValue, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(serde_wasm_bindgen::to_value(&project_issues(&project, &root_id))?)
+}
+
+/// Iterative DFS (no recursion-depth limit): a child already on the current
+/// path is a cycle; one reached again via another branch is just skipped.
+/// Orphans are reported last, sorted by id.
+pub(crate) fn project_issues(project: &HashMap<String, VectraNode>, root_id: &str) -> Vec<ProjectIssue> {
+    let mut out = Vec::new();
+    if !project.contains_key(root_id) {
+        out.push(ProjectIssue { kind: "missing-root", node: root_id.to_string(), child: None });
+        return out;
+    }
+    let mut on_path: HashSet<&str> = HashSet::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut stack: Vec<(&str, usize)> = vec![(root_id, 0)];
+    on_path.insert(root_id); seen.insert(root_id);
+    while let Some(&mut (id, ref mut next)) = stack.last_mut() {
+        let children = project.get(id).and_then(|n| n.children.as_deref()).unwrap_or(&[]);
+        let Some(c) = children.get(*next) else {
+            on_path.remove(id); stack.pop(); continue;
+        };
+        *next += 1;
+        let issue = |kind| ProjectIssue { kind, node: id.to_string(), child: Some(c.clone()) };
+        if !project.contains_key(c) { out.push(issue("dangling-child")); }
+        else if on_path.contains(c.as_str()) { out.push(issue("cycle")); }
+        else if seen.insert(c) { on_path.insert(c); stack.push((c, 0)); }
+    }
+    let mut orphans: Vec<&String> = project.keys().filter(|k| !seen.contains(k.as_str())).collect();
+    orphans.sort();
+    out.extend(orphans.into_iter().map(|o| ProjectIssue { kind: "orphan", node: o.clone(), child: None }));
+    out
+}
+
+/// `vx-<4 hex>-` from the component name — stable across exports of the same component.
+fn scope_prefix(component: &str) -> String {
+    format!("vx-{:04x}-", fnv1a(component) & 0xffff)
 }
 
-fn collect_icons(p: &HashMap<String, VectraNode>, id: &str, icons: &mut HashSet<String>) {
+/// Visits each node once (`seen`), so shared or cyclic children can't
+/// multiply the walk.
+fn collect_icons<'a>(p: &'a HashMap<String, VectraNode>, id: &'a str, icons: &mut HashSet<String>, seen: &mut HashSet<&'a str>, depth_left: usize) {
     let Some(n) = p.get(id) else { return };
+    if depth_left == 0 || is_hidden(n) || !seen.insert(id) { return; }
     if n.other.get("type").and_then(|v| v.as_str()) == Some("icon") {
         if let Some(name) = n.other.get("props")
             .and_then(|p| p.get("iconName")).and_then(|v| v.as_str())
@@ -68,30 +720,442 @@ fn collect_icons(p: &HashMap<String, VectraNode>, id: &str, icons: &mut HashSet<
             icons.insert(name.to_string());
         }
     }
-    if let Some(ch) = &n.children { for c in ch { collect_icons(p, c, icons); } }
+    if let Some(ch) = &n.children { for c in ch { collect_icons(p, c, icons, seen, depth_left - 1); } }
 }
 
-fn gen_node_rec(p: &HashMap<String, VectraNode>, id: &str, buf: &mut String, indent: usize, _parent: Option<&str>) {
-    let Some(n) = p.get(id) else { return };
-    let sp = "  ".repeat(indent);
+/// Cycle guard around `gen_node_body`: a node already on the render path
+/// (A → B → A in a corrupt save) becomes a comment plus a warning instead of
+/// recursing until the depth limit.
+fn gen_node_rec(cx: &mut GenCtx, id: &str, buf: &mut String, indent: usize, depth: usize, parent: Option<&str>) {
+    if !cx.on_path.insert(id.to_string()) {
+        let _ = writeln!(buf, "{}{{/* cycle: \"{}\" skipped */}}", cx.opts.indent.repeat(indent), id);
+        cx.warnings.push(format!("cycle at \"{}\": node is its own ancestor, skipped", id));
+        return;
+    }
+    gen_node_body(cx, id, buf, indent, depth, parent);
+    cx.on_path.remove(id);
+}
+
+fn gen_node_body(cx: &mut GenCtx, id: &str, buf: &mut String, indent: usize, depth: usize, parent: Option<&str>) {
+    let Some(n) = cx.p.get(id) else { return };
+    if is_hidden(n) { return; }
+    let sp = cx.opts.indent.repeat(indent);
+    if depth >= cx.opts.max_depth {
+        let _ = writeln!(buf, "{}{{/* truncated: max depth {} exceeded */}}", sp, cx.opts.max_depth);
+        cx.warnings.push(format!("subtree at \"{}\" truncated: nesting exceeds max depth {}", id, cx.opts.max_depth));
+        return;
+    }
     let nt    = n.other.get("type").and_then(|v| v.as_str()).unwrap_or("div");
     let props = n.other.get("props");
     let content = n.other.get("content").and_then(|v| v.as_str()).unwrap_or("");
-    let cls = props.and_then(|p| p.get("className")).and_then(|v| v.as_str()).unwrap_or("");
-    let ps = if cls.is_empty() { String::new() } else { format!(" className=\"{}\"", cls) };
-    let tag = match nt {
-        "text"|"paragraph" => "p", "heading" => "h1", "button" => "button",
-        "image" => "img", "input" => "input", "canvas"|"webpage" => "main", _ => "div",
-    };
+    let mut classes: Vec<String> = props.and_then(|p| p.get("className")).and_then(|v| v.as_str())
+        .map(|c| c.split_whitespace().map(String::from).collect()).unwrap_or_default();
+    if let Some(t) = props.and_then(|p| p.get("transition")) { transition_classes(t, &mut classes); }
+    if nt == "image" { object_fit_classes(props, &mut classes); }
+    if cx.opts.rtl { classes = classes.iter().map(|c| logical_class(c)).collect(); }
+    let style = props.and_then(|p| p.get("style")).and_then(|v| v.as_object());
+    let mut inline: Vec<String> = Vec::new();
+    let mut sheet_style = if cx.opts.rtl { style.map(logical_style) } else { style.cloned() };
+    if !cx.theme.is_empty() {
+        if let Some(st) = sheet_style.as_mut() {
+            st.retain(|k, v| match color_literal(v).and_then(|c| cx.theme.get(&c)) {
+                Some(tok) => { inline.push(format!("{}: theme.colors.{}", js_key(k), tok)); false }
+                None => true,
+            });
+        }
+    }
+    match (cx.opts.style_mode, sheet_style.as_ref()) {
+        (StyleMode::CssModule, Some(st)) => if let Some(c) = cx.style_class(id, st) { classes.insert(0, c); },
+        (StyleMode::Inline, Some(st)) => inline.extend(st.iter()
+            .filter_map(|(k, v)| Some(format!("{}: {}", js_key(k), js_style_value(v)?)))),
+        _ => {}
+    }
+    let mut ps = String::new();
+    // In a <ul>/<ol> the key goes on the wrapping <li> instead.
+    if parent.is_some_and(|pid| repeated_children(cx.p, pid) && !matches!(node_tag(cx.p, pid), Some("ul"|"ol"))) {
+        push_str_attr(&mut ps, "key", id);
+    }
+    // A component below the export root renders as `<Name />`; its subtree is
+    // generated once, as the definition, the first time the name is seen.
+    // The definition walk passes no parent, so its root isn't replaced again.
+    // Each instance passes its own text for the component's `propName`s.
+    if let Some(cname) = parent.and_then(|_| component_ident(n, &cx.export_name)) {
+        if !cx.components.iter().any(|c| c.name == cname) {
+            cx.components.push(ComponentDef { name: cname.clone(), jsx: String::new(), props: Vec::new() });
+            let outer = std::mem::take(&mut cx.props);
+            let mut jsx = String::new();
+            gen_node_body(cx, id, &mut jsx, 2, depth, None);
+            let props = std::mem::replace(&mut cx.props, outer);
+            if let Some(c) = cx.components.iter_mut().find(|c| c.name == cname) { c.jsx = jsx; c.props = props; }
+        }
+        for (k, v) in instance_props(cx.p, id) { push_str_attr(&mut ps, &k, &v); }
+        let _ = writeln!(buf, "{}<{}{} />", sp, cname, ps);
+        return;
+    }
+    if !classes.is_empty() { push_str_attr(&mut ps, "className", &classes.join(" ")); }
+    let tag = html_tag(nt, props);
+    let explicit_role = cx.opts.a11y && push_a11y_attrs(cx, &mut ps, n, tag);
+    push_event_attrs(&mut ps, n, tag, explicit_role, cx.opts.typescript);
+    if depth == 0 {
+        if cx.opts.rtl { ps.push_str(" dir=\"rtl\""); }
+        let decls = root_size_decls(cx, n);
+        // The root size wins over a same-named `props.style` entry.
+        inline.retain(|e| !decls.iter().any(|d| d.split(':').next() == e.split(':').next()));
+        inline.splice(0..0, decls);
+    }
+    if !inline.is_empty() { let _ = write!(ps, " style={{{{ {} }}}}", inline.join(", ")); }
+    let next_image = tag == "img" && cx.opts.next_image;
+    if tag == "img" {
+        if let Some(src) = image_src(n) { push_str_attr(&mut ps, "src", src); }
+        match prop_str(props, "alt") {
+            Some(alt) => push_str_attr(&mut ps, "alt", alt),
+            // next/image requires `alt`.
+            None if cx.opts.a11y || next_image => ps.push_str(" alt=\"\""),
+            None => {}
+        }
+    }
+    if next_image {
+        cx.next_image = true;
+        let dim = |k: &str| [style.and_then(|s| s.get(k)), props.and_then(|p| p.get(k))]
+            .into_iter().map(px_val).find(|v| *v > 0.0);
+        match (dim("width"), dim("height")) {
+            (Some(w), Some(h)) => { let _ = write!(ps, " width={{{}}} height={{{}}}", w, h); }
+            _ => {
+                ps.push_str(" fill");
+                cx.warnings.push(format!("image \"{}\" has no px width/height: exported with `fill`", id));
+            }
+        }
+        let _ = writeln!(buf, "{}<Image{} />", sp, ps);
+        return;
+    }
+    if tag == "input" {
+        // `defaultValue`, not `value`: a static export has no onChange to control it.
+        for (key, attr) in [("type", "type"), ("placeholder", "placeholder"), ("value", "defaultValue")] {
+            if let Some(v) = prop_str(props, key) { push_str_attr(&mut ps, attr, v); }
+        }
+        for (keys, attr) in [(&["disabled"][..], "disabled"), (&["readOnly", "readonly"][..], "readOnly"), (&["required"][..], "required")] {
+            push_bool_attr(&mut ps, props, keys, attr);
+        }
+    }
     if matches!(tag, "img"|"input") { let _ = writeln!(buf, "{}<{}{} />", sp, tag, ps); return; }
     let mut cb = String::new();
-    if !content.is_empty() { cb.push_str(content); }
-    if let Some(ch) = &n.children { for c in ch { gen_node_rec(p, c, &mut cb, indent+1, Some(id)); } }
+    match prop_name(n) {
+        Some(pn) => {
+            let _ = write!(cb, "{{props.{}}}", pn);
+            if !cx.props.iter().any(|p| p == pn) { cx.props.push(pn.to_string()); }
+        }
+        None if !content.is_empty() => cb.push_str(&jsx_text(content)),
+        None => {}
+    }
+    if let Some(ch) = &n.children {
+        if matches!(tag, "ul"|"ol") {
+            // Each list child becomes one <li>; the <li> is markup only, not a tree level.
+            let lsp = cx.opts.indent.repeat(indent + 1);
+            let keyed = repeated_children(cx.p, id);
+            for c in ch.iter().filter(|c| cx.p.get(*c).is_some_and(|n| !is_hidden(n))) {
+                let mut item = String::new();
+                gen_node_rec(cx, c, &mut item, indent+2, depth+1, Some(id));
+                let mut li = String::from("li");
+                if keyed { push_str_attr(&mut li, "key", c); }
+                let _ = write!(cb, "{}<{}>\n{}{}</li>\n", lsp, li, item, lsp);
+            }
+        } else {
+            for c in ch { gen_node_rec(cx, c, &mut cb, indent+1, depth+1, Some(id)); }
+        }
+    }
     if cb.is_empty() { let _ = writeln!(buf, "{}<{}{} />", sp, tag, ps); }
     else if cb.contains('\n') { let _ = writeln!(buf, "{}<{}{}>\n{}{}</{}>", sp, tag, ps, cb, sp, tag); }
     else { let _ = writeln!(buf, "{}<{}{}>{}</{}>", sp, tag, ps, cb, tag); }
 }
 
+/// Node type → HTML tag, shared by the React and Vue walks.
+fn html_tag(nt: &str, props: Option<&Value>) -> &'static str {
+    match nt {
+        "text"|"paragraph" => "p", "heading" => "h1", "button" => "button",
+        "image" => "img", "input" => "input", "canvas"|"webpage" => "main",
+        "list" => if props.and_then(|p| p.get("ordered")).and_then(|v| v.as_bool()) == Some(true) { "ol" } else { "ul" },
+        _ => "div",
+    }
+}
+
+/// `propName` of a prop-driven text node, when it is a usable prop identifier.
+fn prop_name(n: &VectraNode) -> Option<&str> {
+    n.other.get("propName").and_then(|v| v.as_str()).filter(|s| is_js_ident(s) && !matches!(*s, "key"|"ref"))
+}
+
+/// `(propName, content)` for the text nodes of one component instance,
+/// stopping at nested components — those take their own props. Hidden
+/// subtrees don't render, so they pass nothing.
+fn instance_props(p: &HashMap<String, VectraNode>, id: &str) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut stack = vec![id];
+    while let Some(cur) = stack.pop() {
+        let Some(n) = p.get(cur) else { continue };
+        if is_hidden(n) || !seen.insert(cur) || (cur != id && component_ident(n, "").is_some()) { continue; }
+        if let Some(pn) = prop_name(n) {
+            if !out.iter().any(|(k, _)| k == pn) {
+                let text = n.other.get("content").and_then(|v| v.as_str()).unwrap_or("");
+                out.push((pn.to_string(), text.to_string()));
+            }
+        }
+        // Reversed so the first child is visited first, matching the render order.
+        for c in n.children.iter().flatten().rev() { stack.push(c); }
+    }
+    out
+}
+
+/// `<name>Props` interface (TypeScript mode) for a function reading `props`;
+/// returns the parameter list — empty when the function takes no props.
+fn push_props_interface(code: &mut String, name: &str, props: &[String], opts: &ExportOptions) -> String {
+    if props.is_empty() { return String::new(); }
+    if !opts.typescript { return "props".into(); }
+    let u = opts.indent.as_str();
+    let _ = write!(code, "\ninterface {}Props {{\n", name);
+    for p in props { let _ = writeln!(code, "{u}{}: string;", p); }
+    code.push_str("}\n");
+    format!("props: {}Props", name)
+}
+
+/// `componentName` (else `name`) of an `isComponent: true` node as a
+/// PascalCase identifier — JSX treats lowercase tags as HTML elements.
+/// `Component` is appended when it would shadow the default export.
+fn component_ident(n: &VectraNode, export_name: &str) -> Option<String> {
+    if n.other.get("isComponent").and_then(|v| v.as_bool()) != Some(true) { return None; }
+    let raw = ["componentName", "name"].iter()
+        .find_map(|k| n.other.get(*k).and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty()))
+        .unwrap_or("Component");
+    let mut name: String = raw.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty())
+        .map(|w| { let mut c = w.chars(); c.next().map(|f| f.to_uppercase().chain(c).collect::<String>()).unwrap_or_default() })
+        .collect();
+    if !name.starts_with(|c: char| c.is_alphabetic()) { name = format!("Component{}", name); }
+    if name == export_name { name.push_str("Component"); }
+    Some(name)
+}
+
+fn node_tag(p: &HashMap<String, VectraNode>, id: &str) -> Option<&'static str> {
+    let n = p.get(id)?;
+    Some(html_tag(n.other.get("type").and_then(|v| v.as_str()).unwrap_or("div"), n.other.get("props")))
+}
+
+/// Two or more children, all of one `type` — a repeated list that React
+/// wants `key`ed. Node ids are unique in the project, so they are the keys.
+fn repeated_children(p: &HashMap<String, VectraNode>, id: &str) -> bool {
+    let Some(ch) = p.get(id).and_then(|n| n.children.as_ref()) else { return false };
+    let mut types = ch.iter().filter_map(|c| p.get(c)).filter(|n| !is_hidden(n))
+        .map(|n| n.other.get("type").and_then(|v| v.as_str()).unwrap_or("div"));
+    let Some(first) = types.next() else { return false };
+    let mut count = 1;
+    for t in types { if t != first { return false; } count += 1; }
+    count >= 2
+}
+
+/// Root style entries per `ExportOptions.root_size`; the size comes from the
+/// export root, falling back to the page/frame it was unwrapped from.
+fn root_size_decls(cx: &GenCtx, n: &VectraNode) -> Vec<String> {
+    if cx.opts.root_size == RootSize::None { return Vec::new(); }
+    let dim = |k: &str| [Some(n), cx.p.get(cx.root_id)].into_iter().flatten()
+        .map(|m| px_val(m.other.get("props").and_then(|p| p.get("style")).and_then(|s| s.get(k))))
+        .find(|v| *v > 0.0);
+    let (w, h) = (dim("width"), dim("height"));
+    match cx.opts.root_size {
+        RootSize::Fixed => [("width", w), ("height", h)].iter()
+            .filter_map(|(k, v)| v.map(|v| format!("{}: {}", k, v))).collect(),
+        _ => vec!["width: '100%'".into(), format!("minHeight: {}", h.map(|h| h.to_string()).unwrap_or_else(|| "'100vh'".into()))],
+    }
+}
+
+/// Every `events` entry (node level wins over props level) whose key is an
+/// `onXxx` name and whose value is a bare identifier → `onXxx={handler}`.
+/// Anything else is skipped rather than emitted as broken JSX.
+fn event_handlers(n: &VectraNode) -> BTreeMap<&str, &str> {
+    let mut out = BTreeMap::new();
+    for ev in [n.other.get("events"), n.other.get("props").and_then(|p| p.get("events"))].into_iter().flatten() {
+        let Some(ev) = ev.as_object() else { continue };
+        for (k, v) in ev {
+            let ok = k.strip_prefix("on").is_some_and(|r| r.starts_with(|c: char| c.is_ascii_uppercase())) && is_js_ident(k);
+            if let Some(h) = v.as_str().filter(|h| ok && is_js_ident(h)) { out.entry(k.as_str()).or_insert(h); }
+        }
+    }
+    out
+}
+
+/// Emit the node's event handlers. `onClick` on a non-interactive tag also
+/// gets `role="button"`, `tabIndex={0}` and — unless the node has its own
+/// `onKeyDown` — an Enter/Space key handler (jsx-a11y
+/// click-events-have-key-events + no-static-element-interactions).
+fn push_event_attrs(ps: &mut String, n: &VectraNode, tag: &str, explicit_role: bool, ts: bool) {
+    let handlers = event_handlers(n);
+    for (k, h) in &handlers { let _ = write!(ps, " {}={{{}}}", k, h); }
+    let Some(h) = handlers.get("onClick") else { return };
+    if matches!(tag, "button"|"a"|"input"|"select"|"textarea") { return; }
+    if !explicit_role { ps.push_str(" role=\"button\""); }
+    ps.push_str(" tabIndex={0}");
+    if handlers.contains_key("onKeyDown") { return; }
+    let e = if ts { "e: React.KeyboardEvent" } else { "e" };
+    let _ = write!(ps, " onKeyDown={{({}) => {{ if (e.key === 'Enter' || e.key === ' ') {}(e); }}}}", e, h);
+}
+
+/// `role` and `aria-label` from props; a `button` whose only content is one
+/// icon child gets its label from the icon name (`ArrowRight` → "Arrow right").
+/// Returns whether a role was written, so the onClick role isn't doubled.
+fn push_a11y_attrs(cx: &GenCtx, ps: &mut String, n: &VectraNode, tag: &str) -> bool {
+    let props = n.other.get("props");
+    let role = prop_str(props, "role");
+    if let Some(r) = role { push_str_attr(ps, "role", r); }
+    let derived = || {
+        if tag != "button" || n.other.get("content").and_then(|v| v.as_str()).is_some_and(|c| !c.trim().is_empty()) { return None; }
+        let [only] = n.children.as_deref()? else { return None };
+        let child = cx.p.get(only)?;
+        if child.other.get("type").and_then(|v| v.as_str()) != Some("icon") { return None; }
+        prop_str(child.other.get("props"), "iconName").map(icon_label)
+    };
+    if let Some(label) = prop_str(props, "ariaLabel").map(String::from).or_else(derived) {
+        push_str_attr(ps, "aria-label", &label);
+    }
+    role.is_some()
+}
+
+/// `ArrowRight` → "Arrow right", `XCircle` → "X circle".
+fn icon_label(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if i > 0 && c.is_uppercase() { out.push(' '); out.extend(c.to_lowercase()); } else { out.push(c); }
+    }
+    out
+}
+
+/// `props.objectFit` / `props.objectPosition` → `object-*` utilities.
+/// An image with explicit width and height but no fit defaults to `object-cover`
+/// so it crops instead of stretching.
+fn object_fit_classes(props: Option<&Value>, out: &mut Vec<String>) {
+    let get = |k: &str| props.and_then(|p| p.get(k)).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    let style = props.and_then(|p| p.get("style"));
+    let sized = ["width", "height"].iter().all(|k| px_val(style.and_then(|s| s.get(*k))) > 0.0);
+    match get("objectFit") {
+        Some(f @ ("cover"|"contain"|"fill"|"none"|"scale-down")) => out.push(format!("object-{}", f)),
+        Some("scaleDown") => out.push("object-scale-down".into()),
+        Some(_) => {}
+        None if sized => out.push("object-cover".into()),
+        None => {}
+    }
+    if let Some(pos) = get("objectPosition") {
+        let words: Vec<&str> = pos.split_whitespace().collect();
+        const NAMED: &[&str] = &["center", "top", "bottom", "left", "right", "left-top", "left-bottom", "right-top", "right-bottom"];
+        let named = words.join("-");
+        out.push(if NAMED.contains(&named.as_str()) { format!("object-{}", named) } else { format!("object-[{}]", words.join("_")) });
+    }
+}
+
+/// `props.transition` → Tailwind transition utilities.
+/// Object form: `{ properties, duration, easing, delay, hover }` where
+/// `hover` is a class string re-emitted with the `hover:` variant.
+/// A plain CSS string ("all 200ms ease") becomes an arbitrary `[transition:…]`.
+fn transition_classes(t: &Value, out: &mut Vec<String>) {
+    if let Some(css) = t.as_str() {
+        if !css.trim().is_empty() { out.push(format!("[transition:{}]", css.split_whitespace().collect::<Vec<_>>().join("_"))); }
+        return;
+    }
+    if !t.is_object() { return; }
+    let props: Vec<String> = match t.get("properties") {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(a)) => a.iter().filter_map(|v| v.as_str().map(String::from)).collect(),
+        _ => Vec::new(),
+    };
+    out.push(match props.as_slice() {
+        [] => "transition".into(),
+        [one] => match one.as_str() {
+            "all"|"colors"|"opacity"|"shadow"|"transform" => format!("transition-{}", one),
+            "none" => "transition-none".into(),
+            other => format!("transition-[{}]", camel_to_kebab(other)),
+        },
+        many => format!("transition-[{}]", many.iter().map(|p| camel_to_kebab(p)).collect::<Vec<_>>().join(",")),
+    });
+    const STEPS: &[u32] = &[0, 75, 100, 150, 200, 300, 500, 700, 1000];
+    for (key, prefix) in [("duration", "duration"), ("delay", "delay")] {
+        if let Some(ms) = t.get(key).and_then(css_time_ms) {
+            out.push(if STEPS.contains(&ms) { format!("{}-{}", prefix, ms) } else { format!("{}-[{}ms]", prefix, ms) });
+        }
+    }
+    if let Some(e) = t.get("easing").and_then(|v| v.as_str()) {
+        out.push(match e {
+            "linear"|"ease-in"|"ease-out"|"ease-in-out" => format!("ease-{}", e.trim_start_matches("ease-")),
+            "ease" => "ease-[ease]".into(),
+            other => format!("ease-[{}]", other.replace(' ', "")),
+        });
+    }
+    if let Some(h) = t.get("hover").and_then(|v| v.as_str()) {
+        out.extend(h.split_whitespace().map(|c| format!("hover:{}", c)));
+    }
+}
+
+/// 200 | "200" | "200ms" | "0.2s" → 200
+fn css_time_ms(v: &Value) -> Option<u32> {
+    if let Some(n) = v.as_f64() { return Some(n.max(0.0).round() as u32); }
+    let s = v.as_str()?.trim();
+    let ms = if let Some(x) = s.strip_suffix("ms") { x.trim().parse::<f64>().ok()? }
+             else if let Some(x) = s.strip_suffix('s') { x.trim().parse::<f64>().ok()? * 1000.0 }
+             else { s.parse::<f64>().ok()? };
+    Some(ms.max(0.0).round() as u32)
+}
+
+/// Non-empty string prop.
+fn prop_str<'a>(props: Option<&'a Value>, key: &str) -> Option<&'a str> {
+    props?.get(key)?.as_str().filter(|s| !s.is_empty())
+}
+
+/// `props.src`, falling back to the node-level `src` the editor stores.
+fn image_src(n: &VectraNode) -> Option<&str> {
+    prop_str(n.other.get("props"), "src").or_else(|| n.other.get("src")?.as_str().filter(|s| !s.is_empty()))
+}
+
+/// Literal text child; anything JSX would parse (`{ } < >`, or `&` as an
+/// entity) goes in a `{"…"}` expression container instead — "Price: <$10>"
+/// and "&amp;" stay literal.
+fn jsx_text(s: &str) -> String {
+    if s.contains(['{', '}', '<', '>', '&']) { format!("{{{}}}", Value::from(s)) } else { s.to_string() }
+}
+
+/// String JSX attribute — quoted when safe, else a JS string literal in braces.
+fn push_str_attr(ps: &mut String, attr: &str, v: &str) {
+    if v.contains(['"', '{', '}', '\\', '\n', '&']) { let _ = write!(ps, " {}={{{}}}", attr, Value::from(v)); }
+    else { let _ = write!(ps, " {}=\"{}\"", attr, v); }
+}
+
+/// Object-literal key: bare when it is an identifier, else quoted (`'--gap'`).
+fn js_key(k: &str) -> String {
+    if is_js_ident(k) { k.to_string() } else { Value::from(k).to_string() }
+}
+
+/// Style value as a JS literal: numbers stay numbers (React adds `px`),
+/// strings are quoted; null / objects / arrays are dropped.
+fn js_style_value(v: &Value) -> Option<String> {
+    match v {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(Value::from(s.as_str()).to_string()),
+        Value::Bool(b)   => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// True for a bare JS identifier (`isLocked`, `_x1`) — safe inside `{…}`.
+fn is_js_ident(s: &str) -> bool {
+    let mut ch = s.chars();
+    ch.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && ch.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Boolean JSX attribute from the first prop key present:
+/// `true` → bare `disabled`, identifier string → `disabled={binding}`, else nothing.
+fn push_bool_attr(ps: &mut String, props: Option<&Value>, keys: &[&str], attr: &str) {
+    let Some(v) = keys.iter().find_map(|k| props.and_then(|p| p.get(*k))) else { return };
+    match v {
+        Value::Bool(true) => { let _ = write!(ps, " {}", attr); }
+        Value::String(b) if is_js_ident(b) => { let _ = write!(ps, " {}={{{}}}", attr, b); }
+        _ => {}
+    }
+}
+
 // ── §17 collect_stack_on_mobile_ids ──────────────────────────────────────────
 
 /// Walk project subtree, return IDs where props.stackOnMobile === true.
@@ -142,3 +1206,322 @@ pub fn slug_to_next_path(slug: &str) -> String {
     if clean.is_empty() { return "app/page.tsx".to_string(); }
     format!("app/{}/page.tsx", clean)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Project map from `{ id: node }` JSON; each node gets its `id` filled in.
+    fn project(v: Value) -> HashMap<String, VectraNode> {
+        v.as_object().unwrap().iter().map(|(id, n)| {
+            let mut n = n.clone();
+            n["id"] = json!(id);
+            (id.clone(), serde_json::from_value(n).unwrap())
+        }).collect()
+    }
+
+    #[test]
+    fn input_boolean_attributes() {
+        let p = project(json!({
+            "in": { "type": "input", "props": { "disabled": true, "readOnly": "isLocked", "required": false } },
+        }));
+        let code = react_code(&p, "in");
+        assert!(code.contains("<input disabled readOnly={isLocked} />"), "{}", code);
+        assert!(!code.contains("disabled={true}") && !code.contains("required"), "{}", code);
+    }
+
+    #[test]
+    fn pages_export_one_file_each() {
+        let p = project(json!({
+            "home":  { "type": "page", "name": "Home", "children": ["hero"] },
+            "hero":  { "type": "container", "name": "Hero", "children": ["star"] },
+            "star":  { "type": "icon", "props": { "iconName": "Star" } },
+            "about": { "type": "page", "name": "About", "children": ["bio"] },
+            "bio":   { "type": "text", "name": "Bio", "content": "Hi" },
+        }));
+        let files = pages_code(&p);
+        assert_eq!(files.keys().collect::<Vec<_>>(), ["About", "Home"]);
+        assert!(files["Home"].contains("export default function Hero()"), "{}", files["Home"]);
+        assert!(files["Home"].contains("import { Star } from 'lucide-react';"));
+        assert!(files["About"].contains("<p>Hi</p>"), "{}", files["About"]);
+        assert!(!files["About"].contains("lucide-react"));
+    }
+
+    #[test]
+    fn transition_prop_to_classes() {
+        let p = project(json!({
+            "btn": { "type": "button", "content": "Go", "props": {
+                "className": "bg-blue-500",
+                "transition": { "properties": "colors", "duration": "0.2s", "easing": "ease-in-out", "hover": "bg-blue-600" },
+            } },
+            "card": { "type": "container", "props": { "transition": "all 250ms ease" } },
+        }));
+        assert!(react_code(&p, "btn").contains("className=\"bg-blue-500 transition-colors duration-200 ease-in-out hover:bg-blue-600\""));
+        assert!(react_code(&p, "card").contains("className=\"[transition:all_250ms_ease]\""));
+    }
+
+    #[test]
+    fn clickable_div_gets_button_semantics() {
+        let p = project(json!({
+            "card": { "type": "container", "events": { "onClick": "openCard" } },
+            "btn":  { "type": "button", "content": "Go", "events": { "onClick": "go" } },
+        }));
+        let card = react_code(&p, "card");
+        assert!(card.contains("<div onClick={openCard} role=\"button\" tabIndex={0} onKeyDown={(e) => { if (e.key === 'Enter' || e.key === ' ') openCard(e); }} />"), "{}", card);
+        let btn = react_code(&p, "btn");
+        assert!(btn.contains("<button onClick={go}>Go</button>") && !btn.contains("role="), "{}", btn);
+    }
+
+    #[test]
+    fn image_object_fit_classes() {
+        let p = project(json!({
+            "fit":   { "type": "image", "props": { "src": "a.png", "objectFit": "contain", "objectPosition": "left top" } },
+            "sized": { "type": "image", "props": { "src": "b.png", "style": { "width": 320, "height": "180px" } } },
+            "free":  { "type": "image", "props": { "src": "c.png", "style": { "width": 320 } } },
+        }));
+        assert!(react_code(&p, "fit").contains("className=\"object-contain object-left-top\""));
+        assert!(react_code(&p, "sized").contains("className=\"object-cover\""));
+        assert!(!react_code(&p, "free").contains("object-"));
+    }
+
+    fn export(p: &HashMap<String, VectraNode>, root: &str, opts: Value) -> ExportResult {
+        export_react(p, root, &serde_json::from_value(opts).unwrap())
+    }
+
+    /// `n0 → n1 → … → n{len-1}`, each a container.
+    fn chain(len: usize) -> HashMap<String, VectraNode> {
+        let mut v = serde_json::Map::new();
+        for i in 0..len {
+            let ch = if i + 1 < len { json!([format!("n{}", i + 1)]) } else { json!([]) };
+            v.insert(format!("n{}", i), json!({ "type": "container", "children": ch }));
+        }
+        project(Value::Object(v))
+    }
+
+    #[test]
+    fn deep_chain_truncates_at_max_depth() {
+        let p = chain(10);
+        let out = export(&p, "n0", json!({ "maxDepth": 3 }));
+        assert_eq!(out.code.matches("<div").count(), 3);
+        assert!(out.code.contains("{/* truncated: max depth 3 exceeded */}"), "{}", out.code);
+        assert_eq!(out.warnings, ["subtree at \"n3\" truncated: nesting exceeds max depth 3"]);
+
+        // Far past the default limit: truncated, not a stack overflow.
+        let out = export_react(&chain(5_000), "n0", &ExportOptions::default());
+        assert_eq!(out.code.matches("<div").count(), DEFAULT_MAX_DEPTH);
+        assert_eq!(out.warnings.len(), 1);
+    }
+
+    #[test]
+    fn identical_styles_share_one_class() {
+        let card = json!({ "type": "container", "props": { "style": { "padding": 16, "backgroundColor": "#fff" } } });
+        let p = project(json!({
+            "root": { "type": "container", "children": ["a", "b", "c"] },
+            "a": card, "b": card, "c": card,
+        }));
+        let out = export(&p, "root", json!({ "styleMode": "cssModule" }));
+        let css = out.css.unwrap();
+        assert_eq!(css.matches('{').count(), 1, "{}", css);
+        assert!(css.starts_with(".node-1 {"), "{}", css);
+        assert_eq!(out.class_map.len(), 3);
+        assert!(out.class_map.values().all(|c| c == "node-1"));
+        assert_eq!(out.code.matches("className=\"node-1\"").count(), 3, "{}", out.code);
+    }
+
+    #[test]
+    fn display_name_from_page_name() {
+        let p = project(json!({
+            "page": { "type": "page", "name": "Landing page", "children": ["hero"] },
+            "hero": { "type": "container", "name": "Hero" },
+        }));
+        let on = export(&p, "page", json!({ "displayName": true })).code;
+        assert!(on.trim_end().ends_with("Hero.displayName = \"Landing page\";"), "{}", on);
+        assert!(!export(&p, "page", json!({})).code.contains("displayName"));
+    }
+
+    #[test]
+    fn root_carries_dimensions() {
+        let p = project(json!({
+            "frame": { "type": "container", "props": { "style": { "width": 1280, "height": "720px" } }, "children": ["t"] },
+            "t":     { "type": "text", "content": "Hi" },
+        }));
+        let fixed = export(&p, "frame", json!({ "rootSize": "fixed", "styleMode": "tailwind" })).code;
+        assert!(fixed.contains("<div style={{ width: 1280, height: 720 }}>"), "{}", fixed);
+        let fluid = export(&p, "frame", json!({ "rootSize": "responsive", "styleMode": "tailwind" })).code;
+        assert!(fluid.contains("<div style={{ width: '100%', minHeight: 720 }}>"), "{}", fluid);
+        // Only the root is sized.
+        assert!(fluid.contains("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn scoped_class_prefix_is_uniform() {
+        let p = project(json!({
+            "root": { "type": "container", "name": "Pricing", "props": { "style": { "display": "flex" } }, "children": ["a"] },
+            "a":    { "type": "text", "content": "Hi", "props": { "style": { "color": "red" } } },
+        }));
+        let out = export(&p, "root", json!({ "styleMode": "cssModule", "scopeClasses": true }));
+        let prefix = scope_prefix("Pricing");
+        assert!(prefix.starts_with("vx-") && prefix.len() == 8, "{}", prefix);
+        let css = out.css.unwrap();
+        for c in out.class_map.values() {
+            assert!(c.starts_with(&prefix), "{}", c);
+            assert!(css.contains(&format!(".{} {{", c)), "{}", css);
+            assert!(out.code.contains(&format!("className=\"{}\"", c)), "{}", out.code);
+        }
+        assert_eq!(out.class_map.len(), 2);
+    }
+
+    #[test]
+    fn list_node_wraps_children_in_li() {
+        let p = project(json!({
+            "list": { "type": "list", "children": ["a", "b"] },
+            "a": { "type": "text", "content": "One" },
+            "b": { "type": "text", "content": "Two" },
+            "ol": { "type": "list", "props": { "ordered": true }, "children": ["a"] },
+            "box": { "type": "container", "children": ["a", "b"] },
+        }));
+        let ul = react_code(&p, "list");
+        assert!(ul.contains("<ul>\n      <li key=\"a\">\n        <p>One</p>\n      </li>\n      <li key=\"b\">\n        <p>Two</p>\n      </li>\n    </ul>"), "{}", ul);
+        assert!(react_code(&p, "ol").contains("<ol>\n      <li>\n        <p>One</p>\n      </li>\n    </ol>"));
+        // Other groupings stay plain siblings.
+        let div = react_code(&p, "box");
+        assert!(!div.contains("<li") && div.contains("<p key=\"a\">One</p>"), "{}", div);
+    }
+
+    #[test]
+    fn theme_provider_and_token_refs() {
+        let p = project(json!({
+            "root": { "type": "container", "name": "Card", "props": { "style": { "backgroundColor": "#FF0000", "padding": 8 } }, "children": ["t"] },
+            "t":    { "type": "text", "content": "Hi", "props": { "style": { "color": "#ff0000", "borderColor": "#00f" } } },
+        }));
+        let out = export(&p, "root", json!({ "theme": true }));
+        let code = out.code;
+        assert!(code.contains("export const theme = {\n  colors: {\n    brand: \"#ff0000\",\n    color2: \"#00f\",\n  },\n};"), "{}", code);
+        assert!(code.contains("export function ThemeProvider("));
+        assert!(code.contains("<div style={{ backgroundColor: theme.colors.brand, padding: 8 }}>"), "{}", code);
+        assert!(code.contains("color: theme.colors.brand") && code.contains("borderColor: theme.colors.color2"));
+        assert!(code.contains("<ThemeProvider>\n      <CardContent />\n    </ThemeProvider>"), "{}", code);
+        assert!(!code.contains("#FF0000"));
+        let theme = out.theme.unwrap();
+        assert_eq!(theme.get("brand").map(String::as_str), Some("#ff0000"));
+    }
+
+    #[test]
+    fn rtl_export_uses_logical_properties() {
+        let p = project(json!({
+            "root": { "type": "container", "props": { "className": "ml-4 rounded-lg", "style": { "marginLeft": 8 } }, "children": ["t"] },
+            "t":    { "type": "text", "content": "שלום", "props": { "className": "hover:-ml-2 border-lime-500", "style": { "textAlign": "left" } } },
+        }));
+        let code = export(&p, "root", json!({ "rtl": true })).code;
+        assert!(code.contains("<div className=\"ms-4 rounded-lg\" dir=\"rtl\" style={{ marginInlineStart: 8 }}>"), "{}", code);
+        assert!(code.contains("<p className=\"hover:-ms-2 border-lime-500\" style={{ textAlign: \"start\" }}>"), "{}", code);
+        assert_eq!(code.matches("dir=").count(), 1);
+        assert!(!export(&p, "root", json!({})).code.contains("dir="));
+    }
+
+    #[test]
+    fn vue_escapes_text_and_mustaches() {
+        let p = project(json!({
+            "t": { "type": "text", "content": "a < b && {{ x }}" },
+        }));
+        let code = vue_code(&p, "t");
+        assert!(code.contains("<p>a &lt; b &amp;&amp; {{ '{{' }} x }}</p>"), "{}", code);
+    }
+
+    #[test]
+    fn hidden_nodes_skipped_by_every_exporter() {
+        let p = project(json!({
+            "page":  { "type": "page", "name": "Home", "children": ["ghost", "main"] },
+            "ghost": { "type": "container", "hidden": true, "children": ["star"] },
+            "star":  { "type": "icon", "props": { "iconName": "Star" } },
+            "main":  { "type": "container", "name": "Main", "children": ["shy", "t"] },
+            "shy":   { "type": "text", "content": "secret", "hidden": true },
+            "t":     { "type": "text", "content": "shown" },
+        }));
+        let react = react_code(&p, "page");
+        let vue = vue_code(&p, "page");
+        for code in [&react, &vue] {
+            assert!(code.contains("shown"), "{}", code);
+            assert!(!code.contains("secret") && !code.contains("Star"), "{}", code);
+        }
+        assert!(react.contains("export default function Main()") && !react.contains("<>"), "{}", react);
+    }
+
+    #[test]
+    fn jsx_special_text_and_attrs_are_quoted() {
+        assert_eq!(jsx_text("Hello"), "Hello");
+        assert_eq!(jsx_text("Price: <$10>"), "{\"Price: <$10>\"}");
+        assert_eq!(jsx_text("{a}"), "{\"{a}\"}");
+        assert_eq!(jsx_text("Tom &amp; Jerry"), "{\"Tom &amp; Jerry\"}");
+        let mut ps = String::new();
+        push_str_attr(&mut ps, "alt", "plain");
+        push_str_attr(&mut ps, "title", "say \"hi\" & {go}");
+        assert_eq!(ps, " alt=\"plain\" title={\"say \\\"hi\\\" & {go}\"}");
+    }
+
+    #[test]
+    fn page_sections_render_in_a_fragment() {
+        let p = project(json!({
+            "page": { "type": "page", "name": "Landing", "children": ["a", "b", "c"] },
+            "a": { "type": "text", "content": "one" },
+            "b": { "type": "text", "content": "two" },
+            "c": { "type": "text", "content": "three" },
+            "solo": { "type": "page", "name": "Solo", "children": ["a"] },
+        }));
+        let code = react_code(&p, "page");
+        assert!(code.contains("export default function Landing()"), "{}", code);
+        let (open, close) = (code.find("<>").unwrap(), code.find("</>").unwrap());
+        for t in ["one", "two", "three"] {
+            let at = code.find(t).unwrap();
+            assert!(open < at && at < close, "{}", code);
+        }
+        let solo = react_code(&p, "solo");
+        assert!(!solo.contains("<>") && solo.contains("<p>one</p>"), "{}", solo);
+    }
+
+    #[test]
+    fn cycle_is_skipped_with_warning() {
+        let p = project(json!({
+            "A": { "type": "container", "name": "Loop", "children": ["B"] },
+            "B": { "type": "container", "children": ["A", "i"] },
+            "i": { "type": "icon", "props": { "iconName": "Star" } },
+        }));
+        let out = export(&p, "A", json!({}));
+        assert!(out.code.contains("{/* cycle: \"A\" skipped */}"), "{}", out.code);
+        assert_eq!(out.warnings, ["cycle at \"A\": node is its own ancestor, skipped"]);
+        assert!(out.code.contains("import { Star } from 'lucide-react';"), "{}", out.code);
+
+        let (mut icons, mut seen) = (HashSet::new(), HashSet::new());
+        collect_icons(&p, "A", &mut icons, &mut seen, DEFAULT_MAX_DEPTH);
+        assert_eq!(icons, HashSet::from(["Star".to_string()]));
+        assert!(vue_code(&p, "A").contains("<!-- cycle: \"A\" skipped -->"));
+    }
+
+    #[test]
+    fn svelte_attribute_braces_are_literal() {
+        let p = project(json!({
+            "img": { "type": "image", "props": { "src": "/a.png?v={v}", "alt": "Logo {brand}", "className": "[grid-area:{x}]" } },
+        }));
+        let svelte = svelte_code(&p, "img");
+        assert!(svelte.contains("<img class=\"[grid-area:&#123;x&#125;]\" src=\"/a.png?v=&#123;v&#125;\" alt=\"Logo &#123;brand&#125;\" />"), "{}", svelte);
+        assert!(!svelte.contains("{brand}"), "{}", svelte);
+        // Vue only interpolates `{{ }}` in text, so its attributes keep the braces.
+        assert!(vue_code(&p, "img").contains("alt=\"Logo {brand}\""));
+    }
+
+    #[test]
+    fn component_props_from_visible_text() {
+        let p = project(json!({
+            "page":  { "type": "container", "name": "Page", "children": ["card"] },
+            "card":  { "type": "container", "isComponent": true, "componentName": "Card", "children": ["title", "note"] },
+            "title": { "type": "text", "propName": "title", "content": "Hello" },
+            "note":  { "type": "text", "propName": "note", "content": "secret", "hidden": true },
+        }));
+        let code = export(&p, "page", json!({ "typescript": true })).code;
+        assert!(code.contains("interface CardProps {\n  title: string;\n}"), "{}", code);
+        assert!(code.contains("{props.title}"), "{}", code);
+        assert!(code.contains("<Card title=\"Hello\" />"), "{}", code);
+        assert!(!code.contains("note") && !code.contains("secret"), "{}", code);
+    }
+}
@@ -2,7 +2,10 @@
 // compiler.rs  —  §3 SwcCompiler  §13 CodeSanitizer  §14 ComponentAnalyzer  §15 CodeWrapper
 // ══════════════════════════════════════════════════════════════════════════════
 //
-//  §3  SwcCompiler   — TSX→JS (full + minified), JSX validation
+//  §3  SwcCompiler   — TSX→JS (full + minified, classic or automatic runtime), plain JSX→JS,
+//                      optional source-hash output cache, license-comment retention,
+//                      compile_many batch compile,
+//                      compile_target downleveling (?. / ?? / arrows), JSX validation
 //  §13 CodeSanitizer — sanitize_code (import strip, quote fix, icon JSX)
 //                      check_sandbox_violations (eval, fetch, localStorage…)
 //  §14 ComponentAnalyzer — detect_component_name, detect_default_export,
@@ -10,40 +13,160 @@
 //                          get_detection_preview
 //  §15 CodeWrapper   — to_pascal_case, wrap_component_next, wrap_component_vite
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 use swc_core::common::{
-    comments::SingleThreadedComments, sync::Lrc,
-    FileName, Globals, Mark, SourceMap, GLOBALS, Spanned,
+    comments::{Comment, CommentKind, SingleThreadedComments}, sync::Lrc,
+    FileName, Globals, Mark, SourceMap, GLOBALS, Spanned, DUMMY_SP, util::take::Take,
 };
 use swc_core::ecma::{
     codegen::{text_writer::JsWriter, Config, Emitter},
-    parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig},
+    parser::{lexer::Lexer, EsConfig, Parser, StringInput, Syntax, TsConfig},
     transforms::{
+        base::{fixer::fixer, hygiene::hygiene, resolver},
         react::{react, Options as ReactOptions, Runtime},
         typescript::strip,
     },
-    visit::FoldWith,
+    ast::*,
+    utils::{private_ident, undefined, ExprFactory},
+    visit::{FoldWith, VisitMut, VisitMutWith},
 };
-use crate::state::uuid_hex;
+use crate::state::{fnv1a, uuid_hex};
 
 // ── §3 SwcCompiler ────────────────────────────────────────────────────────────
 
+/// Each compile still builds its own `Globals`/`SourceMap` inside
+/// `compile_internal`; the optional cache only holds source / emitted JS
+/// string pairs, so no SWC state outlives a call.
 #[wasm_bindgen]
-pub struct SwcCompiler;
+#[derive(Default)]
+pub struct SwcCompiler {
+    cache: Option<RefCell<CompileCache>>,
+}
+
+/// Outputs retained by a caching compiler; the least recently used is evicted first.
+const COMPILE_CACHE_CAP: usize = 128;
+
+/// FNV-1a of the source → `(source, output)`. The source is compared on every
+/// hit, so a hash collision recompiles instead of returning another file's JS.
+/// `order` runs least → most recently used.
+#[derive(Default)]
+pub(crate) struct CompileCache {
+    map:   HashMap<u64, (String, String)>,
+    order: VecDeque<u64>,
+}
+
+impl CompileCache {
+    pub(crate) fn get(&mut self, code: &str) -> Option<String> {
+        let key = fnv1a(code);
+        let out = self.map.get(&key).filter(|(src, _)| src == code)?.1.clone();
+        self.touch(key);
+        Some(out)
+    }
+
+    pub(crate) fn insert(&mut self, code: String, out: String) {
+        let key = fnv1a(&code);
+        // A colliding source simply replaces the older entry.
+        if self.map.insert(key, (code, out)).is_some() { self.touch(key); return; }
+        self.order.push_back(key);
+        while self.order.len() > COMPILE_CACHE_CAP {
+            if let Some(old) = self.order.pop_front() { self.map.remove(&old); }
+        }
+    }
+
+    fn touch(&mut self, key: u64) {
+        if let Some(i) = self.order.iter().position(|&k| k == key) { self.order.remove(i); }
+        self.order.push_back(key);
+    }
+
+    pub(crate) fn len(&self) -> usize { self.map.len() }
+
+    pub(crate) fn clear(&mut self) { self.map.clear(); self.order.clear(); }
+}
 
 #[wasm_bindgen]
 impl SwcCompiler {
     #[wasm_bindgen(constructor)]
-    pub fn new() -> SwcCompiler { SwcCompiler }
+    pub fn new() -> SwcCompiler { SwcCompiler::default() }
+
+    /// Compiler whose `compile` memoizes the last 128 successful outputs by
+    /// source. Errors are never cached.
+    pub fn new_with_cache() -> SwcCompiler {
+        SwcCompiler { cache: Some(RefCell::new(CompileCache::default())) }
+    }
 
     /// Full TSX → ES5/CJS compilation. Used by compiler.worker.ts.
     pub fn compile(&self, code: String) -> Result<String, JsValue> {
-        compile_internal(code, false)
+        self.compile_cached(code).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Compiles `[[name, code], …]` in one boundary crossing. Returns a plain
+    /// object `{ [name]: { code } | { error } }`; one bad file doesn't stop
+    /// the rest. Each file still gets its own `Globals`, and the cache (if
+    /// enabled) is shared with `compile`.
+    pub fn compile_many(&self, files_val: JsValue) -> Result<JsValue, JsValue> {
+        let files: Vec<(String, String)> = serde_wasm_bindgen::from_value(files_val)?;
+        let out = self.compile_many_map(files);
+        Ok(out.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+    }
+
+    /// Drops every cached output. No-op on an uncached compiler.
+    pub fn clear_cache(&self) {
+        if let Some(c) = &self.cache { c.borrow_mut().clear(); }
+    }
+
+    /// Number of cached outputs (0 when caching is off).
+    pub fn cache_len(&self) -> usize {
+        self.cache.as_ref().map_or(0, |c| c.borrow().len())
+    }
+
+    /// React 17+ automatic runtime — `jsx`/`jsxs` imported from
+    /// `react/jsx-runtime`, so the source needs no `import React`.
+    pub fn compile_automatic(&self, code: String) -> Result<String, JsValue> {
+        compile_internal(code, CompileOpts { runtime: Runtime::Automatic, ..Default::default() })
+    }
+
+    /// Classic-runtime compile downleveled for an older engine. `target` is
+    /// `"es5"`, `"es2015"` … `"es2022"` or `"esnext"`. Below es2020, optional
+    /// chaining (`a?.b`, `a?.()`) and `??` are rewritten to null checks; es5
+    /// also turns arrow functions into `function` expressions (bound when
+    /// they use `this`; arrows reading `arguments` are left as is). Other
+    /// syntax passes through — this is a targeted pass, not preset-env.
+    pub fn compile_target(&self, code: String, target: String) -> Result<String, JsValue> {
+        let t = parse_target(&target)
+            .ok_or_else(|| JsValue::from_str(&format!("[compiler] unknown target: {}", target)))?;
+        compile_internal(code, CompileOpts { target: Some(t), ..Default::default() })
+    }
+
+    /// Plain JS/JSX: ES grammar and no TypeScript strip, so sources the TS
+    /// parser would misread (e.g. `a < b > (c)` as a generic call) compile as written.
+    pub fn compile_jsx(&self, code: String) -> Result<String, JsValue> {
+        compile_internal(code, CompileOpts { typescript: false, ..Default::default() })
+    }
+
+    /// Like `compile`, but license comments (`/*! … */` or anything mentioning
+    /// `@license`) are lifted to the top of the output so they survive even
+    /// when the node they were attached to is stripped (type-only imports,
+    /// interfaces). Required for attribution in exported bundles.
+    pub fn compile_keep_comments(&self, code: String) -> Result<String, JsValue> {
+        compile_internal(code, CompileOpts { legal_comments: true, ..Default::default() })
+    }
+
+    /// Explicit runtime (`"classic"` | `"automatic"`) and minification.
+    pub fn compile_with(&self, code: String, runtime: String, minify: bool) -> Result<String, JsValue> {
+        let rt = match runtime.as_str() {
+            "classic"   => Runtime::Classic,
+            "automatic" => Runtime::Automatic,
+            other => return Err(JsValue::from_str(&format!("[compiler] unknown JSX runtime: {}", other))),
+        };
+        compile_internal(code, CompileOpts { minify, runtime: rt, ..Default::default() })
     }
 
     /// Minified TSX → ES5/CJS. ~35% smaller output for ZIP export.
     pub fn compile_minified(&self, code: String) -> Result<String, JsValue> {
-        compile_internal(code, true)
+        compile_internal(code, CompileOpts { minify: true, ..Default::default() })
     }
 
     /// Parse-only validation. Returns "" if clean or "line:col — parse error".
@@ -67,46 +190,344 @@ impl SwcCompiler {
     }
 }
 
+impl SwcCompiler {
+    fn compile_cached(&self, code: String) -> Result<String, String> {
+        let Some(cache) = &self.cache else { return compile_source(code, CompileOpts::default()) };
+        if let Some(hit) = cache.borrow_mut().get(&code) { return Ok(hit); }
+        let out = compile_source(code.clone(), CompileOpts::default())?;
+        cache.borrow_mut().insert(code, out.clone());
+        Ok(out)
+    }
+
+    pub(crate) fn compile_many_map(&self, files: Vec<(String, String)>) -> BTreeMap<String, FileOutput> {
+        files.into_iter().map(|(name, code)| {
+            let out = match self.compile_cached(code) {
+                Ok(code) => FileOutput { code: Some(code), error: None },
+                Err(e)   => FileOutput { code: None, error: Some(e) },
+            };
+            (name, out)
+        }).collect()
+    }
+}
+
+/// One entry of `compile_many`'s result — exactly one field is set.
+#[derive(Serialize)]
+pub(crate) struct FileOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 /// Free-function alias used by legacy call sites.
 #[wasm_bindgen]
 pub fn compile_component(code: String) -> Result<String, JsValue> {
     SwcCompiler::new().compile(code)
 }
 
-fn compile_internal(code: String, minify: bool) -> Result<String, JsValue> {
+/// Per-call pipeline switches for `compile_internal`.
+struct CompileOpts {
+    minify: bool, runtime: Runtime, target: Option<EsVersion>, typescript: bool, legal_comments: bool,
+}
+
+impl Default for CompileOpts {
+    fn default() -> Self {
+        CompileOpts { minify: false, runtime: Runtime::Classic, target: None, typescript: true, legal_comments: false }
+    }
+}
+
+fn is_legal_comment(c: &Comment) -> bool {
+    (c.kind == CommentKind::Block && c.text.starts_with('!')) || c.text.contains("@license")
+}
+
+/// Removes license comments from the store and returns them in source order.
+fn take_legal_comments(comments: &SingleThreadedComments) -> Vec<Comment> {
+    let mut out = Vec::new();
+    let (mut lead, mut trail) = comments.borrow_all_mut();
+    for map in [&mut *lead, &mut *trail] {
+        for list in map.values_mut() {
+            let (legal, rest): (Vec<_>, Vec<_>) = list.drain(..).partition(is_legal_comment);
+            *list = rest;
+            out.extend(legal);
+        }
+    }
+    out.sort_by_key(|c| c.span.lo);
+    out
+}
+
+fn compile_internal(code: String, o: CompileOpts) -> Result<String, JsValue> {
+    compile_source(code, o).map_err(|e| JsValue::from_str(&e))
+}
+
+fn compile_source(code: String, o: CompileOpts) -> Result<String, String> {
+    let CompileOpts { minify, runtime, target, typescript, legal_comments } = o;
     let globals  = Globals::new();
     let cm: Lrc<SourceMap> = Default::default();
     let comments = SingleThreadedComments::default();
     GLOBALS.set(&globals, || {
         let fm  = cm.new_source_file(FileName::Custom("c.tsx".into()), code);
-        let lex = Lexer::new(
-            Syntax::Typescript(TsConfig { tsx: true, decorators: true, ..Default::default() }),
-            Default::default(), StringInput::from(&*fm), Some(&comments),
-        );
-        let program = Parser::new_from(lex).parse_program().map_err(|e| {
-            let loc = cm.lookup_char_pos(e.span().lo);
-            JsValue::from_str(&format!("Parse error at {}:{}", loc.line, loc.col.0 + 1))
-        })?;
+        let syntax = if typescript {
+            Syntax::Typescript(TsConfig { tsx: true, decorators: true, ..Default::default() })
+        } else {
+            Syntax::Es(EsConfig { jsx: true, decorators: true, ..Default::default() })
+        };
+        let lex = Lexer::new(syntax, Default::default(), StringInput::from(&*fm), Some(&comments));
+        let program = Parser::new_from(lex).parse_program()
+            .map_err(|e| parse_error_json(&cm, &e))?;
+        let legal = if legal_comments { take_legal_comments(&comments) } else { Vec::new() };
         let mark = Mark::new();
-        let mut p = program.fold_with(&mut strip(mark));
+        let mut p = if typescript { program.fold_with(&mut strip(mark)) } else { program };
         p = p.fold_with(&mut react::<SingleThreadedComments>(
             cm.clone(), Some(comments.clone()),
-            ReactOptions { runtime: Some(Runtime::Classic), ..Default::default() },
+            ReactOptions {
+                runtime: Some(runtime),
+                import_source: (runtime == Runtime::Automatic).then(|| "react".into()),
+                ..Default::default()
+            },
             mark, Mark::new(),
         ));
+        if let Some(t) = target {
+            // resolver → Downlevel → hygiene: temps are fresh-mark `_vx` idents,
+            // and hygiene renames any that clash with source names or each other.
+            p = p.fold_with(&mut resolver(Mark::new(), Mark::new(), false));
+            p.visit_mut_with(&mut Downlevel { target: t, temps: Vec::new() });
+            p = p.fold_with(&mut hygiene());
+        }
+        p = p.fold_with(&mut fixer(None));
         let mut buf = vec![];
+        for c in &legal {
+            let text = match c.kind {
+                CommentKind::Block => format!("/*{}*/\n", c.text),
+                CommentKind::Line  => format!("//{}\n", c.text),
+            };
+            buf.extend_from_slice(text.as_bytes());
+        }
         {
             let mut em = Emitter {
-                cfg: Config::default().with_minify(minify),
+                cfg: Config::default().with_minify(minify).with_target(target.unwrap_or(EsVersion::EsNext)),
                 cm: cm.clone(), comments: Some(&comments),
                 wr: JsWriter::new(cm, "\n", &mut buf, None),
             };
-            em.emit_program(&p).map_err(|_| JsValue::from_str("Emit Error"))?;
+            em.emit_program(&p).map_err(|_| "Emit Error".to_string())?;
         }
-        String::from_utf8(buf).map_err(|_| JsValue::from_str("UTF-8 Error"))
+        String::from_utf8(buf).map_err(|_| "UTF-8 Error".to_string())
+    })
+}
+
+// ── §3 Downlevel ──────────────────────────────────────────────────────────────
+
+fn parse_target(t: &str) -> Option<EsVersion> {
+    Some(match t.to_ascii_lowercase().as_str() {
+        "es5" => EsVersion::Es5, "es2015" | "es6" => EsVersion::Es2015, "es2016" => EsVersion::Es2016,
+        "es2017" => EsVersion::Es2017, "es2018" => EsVersion::Es2018, "es2019" => EsVersion::Es2019,
+        "es2020" => EsVersion::Es2020, "es2021" => EsVersion::Es2021, "es2022" => EsVersion::Es2022,
+        "esnext" => EsVersion::EsNext,
+        _ => return None,
     })
 }
 
+/// One link of an optional chain after its root: `.prop` / `[expr]` or `(args)`.
+enum Link { Member(MemberProp), Call(Vec<ExprOrSpread>) }
+
+/// Targeted syntax lowering for `compile_target`. Temporaries are hoisted
+/// into one `var` at the top of the enclosing function (or program), so
+/// recursive and re-entrant calls each get their own. Temps in parameter
+/// defaults belong to the scope around the function.
+struct Downlevel { target: EsVersion, temps: Vec<Ident> }
+
+impl Downlevel {
+    fn temp(&mut self) -> Ident {
+        let id = private_ident!("_vx");
+        self.temps.push(id.clone());
+        id
+    }
+
+    /// `e` as a test operand plus the expression to reuse afterwards —
+    /// identifiers and `this` are read twice, anything else goes through a temp.
+    fn reuse(&mut self, e: Expr) -> (Expr, Expr) {
+        if matches!(e, Expr::Ident(_) | Expr::This(_)) { return (e.clone(), e); }
+        let t = self.temp();
+        (e.make_assign_to(op!("="), PatOrExpr::Pat(Box::new(Pat::Ident(t.clone().into())))), Expr::Ident(t))
+    }
+
+    /// Rewrite a whole chain at once so an early `?.` short-circuits every
+    /// later link: `a?.b.c` → `a == null ? void 0 : a.b.c`.
+    fn lower_chain(&mut self, mut e: Expr) -> Expr {
+        let mut links = Vec::new();
+        while let Expr::OptChain(o) = e {
+            match *o.base {
+                OptChainBase::Member(m) => { links.push((o.optional, Link::Member(m.prop))); e = *m.obj; }
+                OptChainBase::Call(c)   => { links.push((o.optional, Link::Call(c.args))); e = *c.callee; }
+            }
+        }
+        links.reverse();
+        e.visit_mut_with(self);
+        for (_, l) in links.iter_mut() {
+            match l { Link::Member(p) => p.visit_mut_with(self), Link::Call(a) => a.visit_mut_with(self) }
+        }
+        self.build(e, links)
+    }
+
+    fn build(&mut self, mut cur: Expr, links: Vec<(bool, Link)>) -> Expr {
+        let mut it = links.into_iter();
+        while let Some((optional, link)) = it.next() {
+            if !optional { cur = apply_link(cur, link); continue; }
+            let (test, next) = match (cur, link) {
+                // `o.f?.()` keeps `this`: (_o = o, _f = _o.f) == null ? void 0 : _f.call(_o)
+                (Expr::Member(m), Link::Call(args)) => {
+                    let simple = matches!(*m.obj, Expr::Ident(_) | Expr::This(_));
+                    let (o_test, o) = self.reuse(*m.obj);
+                    let f = self.temp();
+                    let get = Expr::Member(MemberExpr { span: DUMMY_SP, obj: Box::new(o.clone()), prop: m.prop })
+                        .make_assign_to(op!("="), PatOrExpr::Pat(Box::new(Pat::Ident(f.clone().into()))));
+                    let test = if simple { get } else {
+                        Expr::Seq(SeqExpr { span: DUMMY_SP, exprs: vec![Box::new(o_test), Box::new(get)] })
+                    };
+                    (test, Expr::Ident(f).call_fn(DUMMY_SP, std::iter::once(o.as_arg()).chain(args).collect()))
+                }
+                (c, link) => { let (test, v) = self.reuse(c); (test, apply_link(v, link)) }
+            };
+            return Expr::Cond(CondExpr {
+                span: DUMMY_SP,
+                test: Box::new(test.make_bin(op!("=="), Expr::Lit(Lit::Null(Null { span: DUMMY_SP })))),
+                cons: undefined(DUMMY_SP),
+                alt: Box::new(self.build(next, it.collect())),
+            });
+        }
+        cur
+    }
+
+    fn hoist_temps(&mut self) -> Option<Stmt> {
+        if self.temps.is_empty() { return None; }
+        let decls = self.temps.drain(..).map(|t| VarDeclarator {
+            span: DUMMY_SP, name: Pat::Ident(t.into()), init: None, definite: false,
+        }).collect();
+        Some(Stmt::Decl(Decl::Var(Box::new(VarDecl { span: DUMMY_SP, kind: VarDeclKind::Var, declare: false, decls }))))
+    }
+}
+
+fn apply_link(obj: Expr, link: Link) -> Expr {
+    match link {
+        Link::Member(prop) => Expr::Member(MemberExpr { span: DUMMY_SP, obj: Box::new(obj), prop }),
+        Link::Call(args)   => obj.as_call(DUMMY_SP, args),
+    }
+}
+
+/// `this` used directly in an arrow body (nested non-arrow functions and
+/// classes have their own); `arguments` likewise.
+#[derive(Default)]
+struct LexicalUse { this: bool, arguments: bool }
+
+impl VisitMut for LexicalUse {
+    fn visit_mut_this_expr(&mut self, _: &mut ThisExpr) { self.this = true; }
+    fn visit_mut_ident(&mut self, i: &mut Ident) { if &*i.sym == "arguments" { self.arguments = true; } }
+    fn visit_mut_function(&mut self, _: &mut Function) {}
+    fn visit_mut_class(&mut self, _: &mut Class) {}
+}
+
+/// Index just past the leading directives (`"use strict"` …), where a
+/// hoisted `var` can go without demoting them to plain expressions.
+fn directive_end<'a>(stmts: impl Iterator<Item = Option<&'a Stmt>>) -> usize {
+    stmts.take_while(|s| matches!(s, Some(Stmt::Expr(e)) if matches!(&*e.expr, Expr::Lit(Lit::Str(_))))).count()
+}
+
+impl VisitMut for Downlevel {
+    fn visit_mut_program(&mut self, p: &mut Program) {
+        p.visit_mut_children_with(self);
+        if let Some(var) = self.hoist_temps() {
+            match p {
+                Program::Module(m) => {
+                    let at = directive_end(m.body.iter().map(|i| i.as_stmt()));
+                    m.body.insert(at, ModuleItem::Stmt(var));
+                }
+                Program::Script(s) => { let at = directive_end(s.body.iter().map(Some)); s.body.insert(at, var); }
+            }
+        }
+    }
+
+    /// Parameter defaults are evaluated outside the body's `var` scope, so
+    /// their temps stay with the enclosing function (or program).
+    fn visit_mut_function(&mut self, f: &mut Function) {
+        f.decorators.visit_mut_with(self);
+        f.params.visit_mut_with(self);
+        let outer = std::mem::take(&mut self.temps);
+        f.body.visit_mut_with(self);
+        if let (Some(body), Some(var)) = (f.body.as_mut(), self.hoist_temps()) {
+            let at = directive_end(body.stmts.iter().map(Some));
+            body.stmts.insert(at, var);
+        }
+        self.temps = outer;
+    }
+
+    /// An expression-bodied arrow that needs temps becomes `{ var …; return … }`.
+    /// As with functions, param-default temps go to the enclosing scope.
+    fn visit_mut_arrow_expr(&mut self, a: &mut ArrowExpr) {
+        a.params.visit_mut_with(self);
+        let outer = std::mem::take(&mut self.temps);
+        a.body.visit_mut_with(self);
+        if let Some(var) = self.hoist_temps() {
+            let mut body = match *a.body.take() {
+                BlockStmtOrExpr::BlockStmt(b) => b,
+                BlockStmtOrExpr::Expr(x) => BlockStmt { span: DUMMY_SP, stmts: vec![x.into_return_stmt().into()] },
+            };
+            let at = directive_end(body.stmts.iter().map(Some));
+            body.stmts.insert(at, var);
+            *a.body = BlockStmtOrExpr::BlockStmt(body);
+        }
+        self.temps = outer;
+    }
+
+    fn visit_mut_expr(&mut self, e: &mut Expr) {
+        if self.target < EsVersion::Es2020 && matches!(e, Expr::OptChain(_)) {
+            *e = self.lower_chain(e.take());
+            return;
+        }
+        e.visit_mut_children_with(self);
+        match e {
+            Expr::Bin(b) if self.target < EsVersion::Es2020 && b.op == op!("??") => {
+                let (test, v) = self.reuse(*b.left.take());
+                *e = Expr::Cond(CondExpr {
+                    span: b.span,
+                    test: Box::new(test.make_bin(op!("!="), Expr::Lit(Lit::Null(Null { span: DUMMY_SP })))),
+                    cons: Box::new(v),
+                    alt: b.right.take(),
+                });
+            }
+            Expr::Arrow(a) if self.target < EsVersion::Es2015 && !a.is_generator => {
+                let mut uses = LexicalUse::default();
+                a.body.visit_mut_with(&mut uses);
+                if uses.arguments { return; }
+                let body = match *a.body.take() {
+                    BlockStmtOrExpr::BlockStmt(b) => b,
+                    BlockStmtOrExpr::Expr(x) => BlockStmt { span: DUMMY_SP, stmts: vec![x.into_return_stmt().into()] },
+                };
+                let f = Expr::Fn(FnExpr { ident: None, function: Box::new(Function {
+                    params: a.params.drain(..).map(Param::from).collect(),
+                    decorators: Vec::new(), span: a.span, body: Some(body),
+                    is_generator: false, is_async: a.is_async, type_params: None, return_type: None,
+                }) });
+                *e = if uses.this {
+                    f.make_member(Ident::new("bind".into(), DUMMY_SP)).as_call(DUMMY_SP, vec![Expr::This(ThisExpr { span: DUMMY_SP }).as_arg()])
+                } else { f };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse failure as `{ message, line, column, snippet }` (1-based line and
+/// column; `snippet` is the offending source line) for editor underlines.
+fn parse_error_json(cm: &SourceMap, e: &swc_core::ecma::parser::error::Error) -> String {
+    let loc = cm.lookup_char_pos(e.span().lo);
+    let snippet = loc.file.get_line(loc.line.saturating_sub(1)).map(|l| l.trim_end().to_string()).unwrap_or_default();
+    serde_json::json!({
+        "message": e.kind().msg(),
+        "line":    loc.line,
+        "column":  loc.col.0 + 1,
+        "snippet": snippet,
+    }).to_string()
+}
+
 // ── §13 CodeSanitizer ─────────────────────────────────────────────────────────
 
 /// Clean AI-generated component code before compilation or embedding.
@@ -121,10 +542,9 @@ pub fn sanitize_code(code: String) -> String {
 
     // Stage 2: Normalise smart quotes + zero-width chars
     s = s
-        .replace('\u{201C}', "\"").replace('\u{201D}', "\"")
-        .replace('\u{2018}', "'").replace('\u{2019}', "'")
-        .replace('\u{200B}', "").replace('\u{200C}', "")
-        .replace('\u{200D}', "").replace('\u{FEFF}', "");
+        .replace(['\u{201C}', '\u{201D}'], "\"")
+        .replace(['\u{2018}', '\u{2019}'], "'")
+        .replace(['\u{200B}', '\u{200C}', '\u{200D}', '\u{FEFF}'], "");
 
     // Stage 3: Fix static Icon JSX: <Icon name="Star" /> → <Lucide.Star />
     s = fix_icon_static(&s);
@@ -384,3 +804,71 @@ pub fn wrap_component_vite(raw_code: &str) -> String {
         raw_code
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{react_code, VectraNode};
+    use serde_json::json;
+
+    #[test]
+    fn exported_jsx_special_text_compiles() {
+        let p: HashMap<String, VectraNode> = serde_json::from_value(json!({
+            "btn": { "id": "btn", "type": "button", "content": "Price: <$10> & {more}" },
+        })).unwrap();
+        let tsx = react_code(&p, "btn");
+        let js = compile_source(tsx, CompileOpts::default()).unwrap();
+        assert!(js.contains("\"Price: <$10> & {more}\""), "{}", js);
+    }
+
+    #[test]
+    fn downlevel_temps_hoist_per_function() {
+        let src = "function f(a) {\n  'use strict';\n  var _vx = 1;\n  return a?.b ?? _vx;\n}\nconst g = (o) => o.x ?? 2;\n";
+        let js = compile_source(src.into(), CompileOpts { target: parse_target("es5"), ..Default::default() }).unwrap();
+        // The temp is declared after the directive, and the user's `_vx` is renamed around it.
+        assert!(js.contains("\"use strict\";\n    var _vx;\n    var _vx1 = 1;\n"), "{}", js);
+        assert!(js.contains("!= null ? _vx : _vx1;"), "{}", js);
+        // An expression-bodied arrow gets a block so its own temp stays local.
+        assert!(js.contains("const g = function(o) {\n    var _vx;\n    return (_vx = o.x) != null ? _vx : 2;\n};"), "{}", js);
+        assert!(!js.starts_with("var"), "{}", js);
+
+        // Param defaults can't see the body's `var`: their temps go one scope out.
+        let src = "function outer() {\n  function f(a = x.y?.z) { return a; }\n  const g = (b = x.w ?? 1) => b;\n}\n";
+        let js = compile_source(src.into(), CompileOpts { target: parse_target("es5"), ..Default::default() }).unwrap();
+        assert!(js.contains("function outer() {\n    var _vx, _vx1;\n"), "{}", js);
+        assert!(js.contains("function f(a = (_vx = x.y) == null ? void 0 : _vx.z) {\n        return a;"), "{}", js);
+        assert!(js.contains("function(b = (_vx1 = x.w) != null ? _vx1 : 1) {\n        return b;"), "{}", js);
+    }
+
+    #[test]
+    fn compile_cache_checks_source_and_evicts_lru() {
+        let mut c = CompileCache::default();
+        // A colliding key holding another file's source is a miss, not a wrong hit.
+        c.map.insert(fnv1a("b"), ("a".into(), "A".into()));
+        c.order.push_back(fnv1a("b"));
+        assert_eq!(c.get("b"), None);
+        c.insert("b".into(), "B".into());
+        assert_eq!((c.get("b").as_deref(), c.len()), (Some("B"), 1));
+        c.clear();
+
+        for i in 0..COMPILE_CACHE_CAP { c.insert(format!("s{}", i), format!("o{}", i)); }
+        assert_eq!(c.get("s0").as_deref(), Some("o0"));
+        c.insert("new".into(), "N".into());
+        assert_eq!(c.len(), COMPILE_CACHE_CAP);
+        assert_eq!(c.get("s1"), None);
+        assert!(c.get("s0").is_some() && c.get("new").is_some());
+        c.clear();
+        assert_eq!((c.len(), c.order.len()), (0, 0));
+    }
+
+    #[test]
+    fn caching_compiler_reuses_outputs() {
+        let c = SwcCompiler::new_with_cache();
+        let a = c.compile_cached("const a = <b />;".into()).unwrap();
+        assert_eq!(c.compile_cached("const a = <b />;".into()).unwrap(), a);
+        assert!(c.compile_cached("const = ;".into()).is_err());
+        assert_eq!(c.cache_len(), 1);
+        c.clear_cache();
+        assert_eq!((c.cache_len(), SwcCompiler::new().cache_len()), (0, 0));
+    }
+}
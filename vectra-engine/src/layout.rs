@@ -5,15 +5,32 @@
 //  §1  LayoutEngine — retained-mode snap/gap/overlap/bbox
 //      Maintains a spatial hash grid of all canvas rects.
 //      Called at 60fps during drag; must be zero-allocation on the hot path.
+//      align_nodes — toolbar align / distribute over a selection.
 //
 //  §6  absolute_to_grid — canvas → CSS Grid converter
 //      Converts absolute-positioned nodes to a CSS grid template.
 //      Returns px + fr unit strings for the Header "Convert to Grid" feature.
+//      GridBuilder — incremental add/remove, recompute on build().
+//      detect_layout — row / column / grid guess for the auto-layout exporter.
+//      absolute_to_flex — single row / column → flex descriptor.
+//      absolute_to_grid_css — the grid as a ready stylesheet.
+//      update_grid_item — re-place one moved node against the existing tracks.
+//
+//  §18 SceneQueries — geometry over the VectraProject map
+//      detect_stacking_conflicts: overlapping siblings with ambiguous z-order.
+//      compute_bounds: union rect of a visible subtree.
+//      resolve_z_order: children in zIndex / document paint order.
+//
+//  §20 TextMetrics — measure_text: greedy-wrap text box estimate from an
+//      average char advance, for sizing before the DOM measures.
 
 use std::collections::{HashMap, HashSet};
 use ahash::AHashMap;
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
+use crate::codegen::VectraNode;
+use crate::state::fnv1a;
+use crate::thumbnail::px_val;
 
 // ── §1 Types ──────────────────────────────────────────────────────────────────
 
@@ -25,13 +42,82 @@ pub struct Guide {
     pub end:        f64,
     pub guide_type: String,
     pub gap_px:     f64,
+    /// Unrounded gap (px) between the two elements for `spacing` / `gap`
+    /// guides, for "42px" measurement labels. None on alignment-style guides.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance:   Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SnapResult {
+    pub x: f64, pub y: f64, pub guides: Vec<Guide>,
+    /// `rects` index whose edge/center produced the alignment snap on each
+    /// axis; None when the axis didn't align (spacing / gap snaps included).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_x_index: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_y_index: Option<usize>,
+}
+
+/// `query_distances` result: px gap per side and the `rects` index it was measured to.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Distances {
+    pub left:  Option<f64>, pub right: Option<f64>, pub up: Option<f64>, pub down: Option<f64>,
+    pub left_index: Option<usize>, pub right_index: Option<usize>,
+    pub up_index:   Option<usize>, pub down_index:  Option<usize>,
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct SnapResult { pub x: f64, pub y: f64, pub guides: Vec<Guide> }
+pub struct ResizeSnapResult { pub x: f64, pub y: f64, pub width: f64, pub height: f64, pub guides: Vec<Guide> }
+
+/// `query_group_snapping` result: one offset for every selected element.
+#[derive(Serialize, Deserialize)]
+pub struct GroupSnapResult { pub dx: f64, pub dy: f64, pub guides: Vec<Guide> }
+
+/// `SnapResult` plus which axes snapped (feeds per-axis hold state).
+pub struct SnapHit { pub result: SnapResult, pub snapped_x: bool, pub snapped_y: bool }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Default)]
-pub struct SimpleRect { pub x: f64, pub y: f64, pub w: f64, pub h: f64 }
+pub struct SimpleRect {
+    pub x: f64, pub y: f64, pub w: f64, pub h: f64,
+    /// Radians about the rect's center. Stored rects are normalized to their
+    /// axis-aligned bounding box on load, so this is 0 after `update_rects`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub rotation: f64,
+}
+
+fn is_zero(v: &f64) -> bool { *v == 0.0 }
+
+impl SimpleRect {
+    pub fn new(x: f64, y: f64, w: f64, h: f64) -> SimpleRect { SimpleRect { x, y, w, h, rotation: 0.0 } }
+
+    /// Axis-aligned bounding box of the rotated rect; unrotated rects skip the trig.
+    pub(crate) fn aabb(&self) -> SimpleRect {
+        if self.rotation == 0.0 || !self.rotation.is_finite() { return SimpleRect { rotation: 0.0, ..*self }; }
+        let (sin, cos) = self.rotation.sin_cos();
+        let (bw, bh) = (self.w * cos.abs() + self.h * sin.abs(), self.w * sin.abs() + self.h * cos.abs());
+        let (cx, cy) = (self.x + self.w / 2.0, self.y + self.h / 2.0);
+        SimpleRect::new(cx - bw / 2.0, cy - bh / 2.0, bw, bh)
+    }
+
+    /// Strict interior overlap — touching edges don't count.
+    fn overlaps(&self, o: &SimpleRect) -> bool {
+        self.x < o.x+o.w && self.x+self.w > o.x && self.y < o.y+o.h && self.y+self.h > o.y
+    }
+}
+
+/// A rect seen along one axis: main start/len, cross start/len.
+struct Span { s: f64, l: f64, c: f64, cl: f64 }
+
+impl Span {
+    fn of(r: &SimpleRect, y_axis: bool) -> Span {
+        if y_axis { Span { s: r.y, l: r.h, c: r.x, cl: r.w } } else { Span { s: r.x, l: r.w, c: r.y, cl: r.h } }
+    }
+    fn end(&self) -> f64 { self.s + self.l }
+    fn cross_overlaps(&self, o: &Span) -> bool { self.c < o.c + o.cl && self.c + self.cl > o.c }
+    /// Midpoint of the shared cross-axis range (guide placement).
+    fn cross_mid(&self, o: &Span) -> f64 { (self.c.max(o.c) + (self.c + self.cl).min(o.c + o.cl)) / 2.0 }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct BBox { pub x: f64, pub y: f64, pub w: f64, pub h: f64 }
@@ -39,118 +125,238 @@ pub struct BBox { pub x: f64, pub y: f64, pub w: f64, pub h: f64 }
 #[derive(Serialize, Deserialize)]
 pub struct OverlapPair { pub a: usize, pub b: usize }
 
+/// Spatial-hash bucket lookup result (debug / tooling).
+#[derive(Serialize, Deserialize)]
+pub struct CellInfo { pub gx: i32, pub gy: i32, pub indices: Vec<usize> }
+
+const ENGINE_STATE_VERSION: u32 = 1;
+
+/// `export_state` / `import_state` blob. Missing keys take defaults so older
+/// blobs keep loading as fields are added.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EngineState {
+    pub version:           u32,
+    pub rects:             Vec<SimpleRect>,
+    pub cell_size:         f64,
+    pub capture_threshold: f64,
+    pub hold_threshold:    f64,
+    /// `[orientation, pos]` pairs, as passed to `set_guides`.
+    pub guides:            Vec<(String, f64)>,
+    pub canvas_bounds:     Option<SimpleRect>,
+    /// false when the cell size was pinned with `set_cell_size`.
+    pub auto_cell_size:    bool,
+    /// `set_pixel_grid` step; 0 = off.
+    pub pixel_grid:        f64,
+}
+
+impl Default for EngineState {
+    fn default() -> Self {
+        EngineState {
+            version: ENGINE_STATE_VERSION, rects: Vec::new(), cell_size: 100.0,
+            capture_threshold: DEFAULT_CAPTURE, hold_threshold: DEFAULT_HOLD,
+            guides: Vec::new(), canvas_bounds: None, auto_cell_size: true, pixel_grid: 0.0,
+        }
+    }
+}
+
+/// One SVG path per guide; `guide_type` lets the overlay pick a stroke style.
+#[derive(Serialize, Deserialize)]
+pub struct GuidePath { pub d: String, pub guide_type: String }
+
 // ── §1 LayoutEngine ───────────────────────────────────────────────────────────
 
+const SPACING_TOL:      f64   = 0.5; // px — gaps this close count as equal
+const SPACING_MIN_REFS: usize = 2;   // equal reference gaps needed before spacing snaps
+const DISTANCE_CELLS:   f64   = 3.0; // query_distances search radius, in hash cells
+const DEFAULT_CAPTURE: f64 = 8.0;
+const DEFAULT_HOLD:    f64 = 4.0;
+const VEL_SLOW: f64 = 2.0;   // px/frame — at or below: max stickiness
+const VEL_FAST: f64 = 30.0;  // px/frame — at or above: min threshold
+
+fn velocity_scale(velocity: Option<f64>) -> f64 {
+    let Some(v) = velocity.filter(|v| v.is_finite()) else { return 1.0 };
+    1.5 - ((v.abs() - VEL_SLOW) / (VEL_FAST - VEL_SLOW)).clamp(0.0, 1.0)
+}
+
 #[wasm_bindgen]
 pub struct LayoutEngine {
     rects:     Vec<SimpleRect>,
     grid:      AHashMap<(i32, i32), Vec<usize>>,
     cell_size: f64,
+    capture_threshold: f64,
+    hold_threshold:    f64,
+    /// Per-axis [x, y] "currently snapped" state for `query_snapping_held`.
+    held:      [bool; 2],
+    /// Designer-placed guide positions: [vertical (x), horizontal (y)].
+    manual:    [Vec<f64>; 2],
+    /// Canvas frame — its edges and centers are snap lines.
+    canvas:    Option<SimpleRect>,
+    /// false once `set_cell_size` pins the cell size.
+    auto_cell: bool,
+    /// Pixel-grid step for axes no other snap claimed; 0 = off.
+    pixel_grid: f64,
+}
+
+impl Default for LayoutEngine {
+    fn default() -> Self { Self::new() }
 }
 
 #[wasm_bindgen]
 impl LayoutEngine {
     #[wasm_bindgen(constructor)]
     pub fn new() -> LayoutEngine {
-        LayoutEngine { rects: Vec::new(), grid: AHashMap::new(), cell_size: 100.0 }
+        LayoutEngine {
+            rects: Vec::new(), grid: AHashMap::new(), cell_size: 100.0,
+            capture_threshold: DEFAULT_CAPTURE, hold_threshold: DEFAULT_HOLD, held: [false; 2],
+            manual: [Vec::new(), Vec::new()], canvas: None, auto_cell: true, pixel_grid: 0.0,
+        }
     }
 
     pub fn update_rects(&mut self, rects_val: JsValue) -> Result<(), JsValue> {
         let rects: Vec<SimpleRect> = serde_wasm_bindgen::from_value(rects_val)?;
-        self.rects = rects;
-        let count = self.rects.len();
-        if count > 0 {
-            let total_dim: f64 = self.rects.iter().map(|r| r.w + r.h).sum();
-            self.cell_size = ((total_dim / (count as f64 * 2.0)) * 1.5).max(50.0).min(500.0);
-        }
-        self.grid.clear();
-        for (idx, r) in self.rects.iter().enumerate() {
-            let gx_min = (r.x / self.cell_size).floor() as i32;
-            let gx_max = ((r.x + r.w) / self.cell_size).floor() as i32;
-            let gy_min = (r.y / self.cell_size).floor() as i32;
-            let gy_max = ((r.y + r.h) / self.cell_size).floor() as i32;
-            for gx in gx_min..=gx_max { for gy in gy_min..=gy_max {
-                self.grid.entry((gx, gy)).or_default().push(idx);
-            }}
-        }
+        self.load_rects(rects);
         Ok(())
     }
 
-    pub fn query_snapping(&self, cx: f64, cy: f64, w: f64, h: f64, threshold: f64) -> Result<JsValue, JsValue> {
-        let mut nx = cx; let mut ny = cy;
-        let mut guides: Vec<Guide> = Vec::with_capacity(4);
-        let mut sx = false; let mut sy = false;
+    /// Replace `rects[index]` and re-bucket only that rect (no `cell_size`
+    /// recompute) — for live resize / multi-select drags over large canvases.
+    pub fn update_single_rect(&mut self, index: usize, rect_val: JsValue) -> Result<(), JsValue> {
+        let rect: SimpleRect = serde_wasm_bindgen::from_value(rect_val)?;
+        self.replace_rect(index, rect).map_err(|e| JsValue::from_str(&e))
+    }
 
-        let gx_min = ((cx - threshold) / self.cell_size).floor() as i32;
-        let gx_max = ((cx + w + threshold) / self.cell_size).floor() as i32;
-        let gy_min = ((cy - threshold) / self.cell_size).floor() as i32;
-        let gy_max = ((cy + h + threshold) / self.cell_size).floor() as i32;
+    /// `velocity` (optional, px per pointer-move frame) scales the threshold:
+    /// up to 1.5× at rest for sticky precise snaps, down to 0.5× on fast drags.
+    /// Omitted → fixed threshold.
+    pub fn query_snapping(&self, cx: f64, cy: f64, w: f64, h: f64, threshold: f64, velocity: Option<f64>) -> Result<JsValue, JsValue> {
+        self.query_snapping_xy(cx, cy, w, h, threshold, threshold, velocity)
+    }
 
-        let mut seen = HashSet::new();
-        let mut cands: Vec<usize> = Vec::new();
-        for gx in gx_min..=gx_max { for gy in gy_min..=gy_max {
-            if let Some(idxs) = self.grid.get(&(gx, gy)) {
-                for &i in idxs { if seen.insert(i) { cands.push(i); } }
-            }
-        }}
+    /// `query_snapping` with independent x / y thresholds, used for both the
+    /// per-axis passes and the spatial-hash cell expansion.
+    #[allow(clippy::too_many_arguments)]
+    pub fn query_snapping_xy(&self, cx: f64, cy: f64, w: f64, h: f64, threshold_x: f64, threshold_y: f64, velocity: Option<f64>) -> Result<JsValue, JsValue> {
+        let k = velocity_scale(velocity);
+        Ok(serde_wasm_bindgen::to_value(&self.snap(cx, cy, w, h, threshold_x * k, threshold_y * k).result)?)
+    }
 
-        for &idx in &cands {
-            let s = &self.rects[idx];
-            if !sx {
-                for (t, sv) in [
-                    (nx, s.x), (nx, s.x+s.w/2.0), (nx, s.x+s.w),
-                    (nx+w/2.0, s.x), (nx+w/2.0, s.x+s.w/2.0), (nx+w/2.0, s.x+s.w),
-                    (nx+w, s.x), (nx+w, s.x+s.w/2.0), (nx+w, s.x+s.w),
-                ] {
-                    if (t-sv).abs() < threshold {
-                        nx += sv - t; sx = true;
-                        guides.push(Guide { orientation:"vertical".into(), pos:sv,
-                            start:ny.min(s.y), end:(ny+h).max(s.y+s.h),
-                            guide_type:"align".into(), gap_px:0.0 });
-                        break;
-                    }
-                }
-            }
-            if !sy {
-                for (t, sv) in [
-                    (ny, s.y), (ny, s.y+s.h/2.0), (ny, s.y+s.h),
-                    (ny+h/2.0, s.y), (ny+h/2.0, s.y+s.h/2.0), (ny+h/2.0, s.y+s.h),
-                    (ny+h, s.y), (ny+h, s.y+s.h/2.0), (ny+h, s.y+s.h),
-                ] {
-                    if (t-sv).abs() < threshold {
-                        ny += sv - t; sy = true;
-                        guides.push(Guide { orientation:"horizontal".into(), pos:sv,
-                            start:nx.min(s.x), end:(nx+w).max(s.x+s.w),
-                            guide_type:"align".into(), gap_px:0.0 });
-                        break;
-                    }
-                }
-            }
-            if sx && sy { break; }
-        }
+    /// `query_snapping` that never snaps to the rects at the indices in
+    /// `exclude_json` (a JSON array), e.g. the dragged element itself after a
+    /// partial update — no `update_rects` rebuild needed to drop them.
+    pub fn query_snapping_excluding(&self, current_x: f64, current_y: f64, width: f64, height: f64, threshold: f64, exclude_json: &str) -> Result<JsValue, JsValue> {
+        let exclude: Vec<usize> = serde_json::from_str(exclude_json)
+            .map_err(|e| JsValue::from_str(&format!("[layout] parse exclude: {}", e)))?;
+        let hit = self.snap_excluding(current_x, current_y, width, height, threshold, threshold, &exclude);
+        Ok(serde_wasm_bindgen::to_value(&hit.result)?)
+    }
+
+    /// Multi-selection drag: snaps the selection's bounding box as one rect
+    /// (same priorities and 9-point math as `query_snapping`) and returns the
+    /// `{ dx, dy, guides }` to apply to every member, so they move together.
+    /// Selected rects must be left out of `update_rects`, or the box snaps to
+    /// its own members.
+    pub fn query_group_snapping(&self, group_x: f64, group_y: f64, group_w: f64, group_h: f64, threshold: f64) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.group_snap(group_x, group_y, group_w, group_h, threshold))?)
+    }
 
-        if !sx { if let Some(g) = self.gap_x(nx, w, &cands, threshold) { nx = g.0; guides.extend(g.1); } }
-        if !sy { if let Some(g) = self.gap_y(ny, h, &cands, threshold) { ny = g.0; guides.extend(g.1); } }
+    /// Capture / hold thresholds for `query_snapping_held` ("wide net, tight hold").
+    /// `capture` applies while an axis is free, `hold` once it has snapped —
+    /// so `hold / capture` is the release factor. Velocity scaling multiplies both.
+    pub fn set_snap_thresholds(&mut self, capture: f64, hold: f64) {
+        self.capture_threshold = capture.max(0.0);
+        self.hold_threshold = hold.max(0.0);
+    }
+
+    /// Stateful `query_snapping`: each axis uses the capture threshold until it
+    /// snaps, then the hold threshold until it releases. Call
+    /// `reset_snap_state` when the drag ends.
+    pub fn query_snapping_held(&mut self, cx: f64, cy: f64, w: f64, h: f64, velocity: Option<f64>) -> Result<JsValue, JsValue> {
+        let res = self.snap_held(cx, cy, w, h, velocity);
+        Ok(serde_wasm_bindgen::to_value(&res)?)
+    }
+
+    pub fn reset_snap_state(&mut self) { self.held = [false; 2]; }
 
-        Ok(serde_wasm_bindgen::to_value(&SnapResult { x:nx, y:ny, guides })?)
+    /// Read-only measurement telemetry: gap to the closest sibling on each
+    /// side (siblings must overlap on the cross axis), searched 3 hash cells out.
+    /// Never snaps or moves anything.
+    pub fn query_distances(&self, current_x: f64, current_y: f64, width: f64, height: f64) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.distances(&SimpleRect::new(current_x, current_y, width, height)))?)
+    }
+
+    /// Snap while dragging a resize handle (`"n"`, `"se"`, `"w"`, …): only the
+    /// moving edges snap, the opposite edges stay fixed. Returns
+    /// `{ x, y, width, height, guides }` with guides on the moved edges.
+    pub fn query_resize_snapping(&self, current_x: f64, current_y: f64, width: f64, height: f64, handle: String, threshold: f64) -> Result<JsValue, JsValue> {
+        let res = self.resize_snap(current_x, current_y, width, height, &handle, threshold).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&res)?)
+    }
+
+    /// Pin the spatial-hash cell size (disables the auto heuristic in
+    /// `update_rects`). A new cell size needs a rebuild: the current rects are
+    /// re-bucketed now, and `update_rects` rebuilds as always.
+    pub fn set_cell_size(&mut self, size: f64) -> Result<(), JsValue> {
+        if !(size.is_finite() && size > 0.0) { return Err(JsValue::from_str(&format!("[layout] invalid cell size {}", size))); }
+        self.pin_cell_size(size);
+        Ok(())
+    }
+
+    /// Re-enable the auto cell-size heuristic and rebuild with it.
+    pub fn use_auto_cell_size(&mut self) {
+        self.auto_cell = true;
+        let rects = std::mem::take(&mut self.rects);
+        self.load_rects(rects);
+    }
+
+    pub fn get_cell_size(&self) -> f64 { self.cell_size }
+
+    /// Canvas frame for edge / center snapping (`guide_type: "canvas"`).
+    pub fn set_canvas_bounds(&mut self, x: f64, y: f64, w: f64, h: f64) {
+        self.canvas = Some(SimpleRect::new(x, y, w, h));
+    }
+
+    pub fn clear_canvas_bounds(&mut self) { self.canvas = None; }
+
+    /// Strict design grid: an axis that no guide / sibling / canvas snap
+    /// claimed rounds to the nearest multiple of `step`
+    /// (`guide_type: "pixel-grid"`). 0, negative or non-finite disables it.
+    pub fn set_pixel_grid(&mut self, step: f64) {
+        self.pixel_grid = if step.is_finite() && step > 0.0 { step } else { 0.0 };
+    }
+
+    /// Persistent canvas guides as `[orientation, pos]` pairs
+    /// (`"vertical"` → x, `"horizontal"` → y). Replaces the previous set.
+    pub fn set_guides(&mut self, guides_val: JsValue) -> Result<(), JsValue> {
+        let guides: Vec<(String, f64)> = serde_wasm_bindgen::from_value(guides_val)?;
+        self.load_guides(guides);
+        Ok(())
+    }
+
+    /// Dump rects + configuration as a repro blob for bug reports.
+    /// The spatial hash is not included — `import_state` rebuilds it.
+    pub fn export_state(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.state())?)
+    }
+
+    /// Restore a blob from `export_state`; keeps its cell size and clears snap-hold state.
+    pub fn import_state(&mut self, state_val: JsValue) -> Result<(), JsValue> {
+        let st: EngineState = serde_wasm_bindgen::from_value(state_val)?;
+        self.restore(st).map_err(|e| JsValue::from_str(&e))
     }
 
     pub fn find_overlapping_pairs(&self) -> Result<String, JsValue> {
         let mut pairs: Vec<OverlapPair> = Vec::new();
         let mut checked = HashSet::<(usize,usize)>::new();
         for (ia, ra) in self.rects.iter().enumerate() {
-            let gx_min=(ra.x/self.cell_size).floor() as i32;
-            let gx_max=((ra.x+ra.w)/self.cell_size).floor() as i32;
-            let gy_min=(ra.y/self.cell_size).floor() as i32;
-            let gy_max=((ra.y+ra.h)/self.cell_size).floor() as i32;
+            let (gx_min, gx_max, gy_min, gy_max) = self.cell_span(ra);
             for gx in gx_min..=gx_max { for gy in gy_min..=gy_max {
                 if let Some(b) = self.grid.get(&(gx,gy)) {
-                    for &ib in b { if ib > ia && checked.insert((ia,ib)) {
-                        let rb=&self.rects[ib];
-                        if ra.x<rb.x+rb.w && ra.x+ra.w>rb.x && ra.y<rb.y+rb.h && ra.y+ra.h>rb.y {
+                    for &ib in b {
+                        if ib > ia && checked.insert((ia,ib)) && ra.overlaps(&self.rects[ib]) {
                             pairs.push(OverlapPair{a:ia,b:ib});
                         }
-                    }}
+                    }
                 }
             }}
         }
@@ -173,6 +379,326 @@ impl LayoutEngine {
 
     pub fn get_rect_count(&self) -> usize { self.rects.len() }
 
+    /// Union `{ x, y, w, h }` of every loaded rect ("zoom to fit all");
+    /// `undefined` when no rects are loaded.
+    pub fn content_bounds(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.content_bbox())?)
+    }
+
+    /// Spatial-hash cell containing (x, y) and the rect indices bucketed there.
+    /// Read-only — exposes the bucketing math for tooling and tests.
+    pub fn cell_for_point(&self, x: f64, y: f64) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.cell_info(x, y))?)
+    }
+
+    /// Replace all rects and rebuild the spatial hash (cell size re-derived).
+    pub(crate) fn load_rects(&mut self, rects: Vec<SimpleRect>) {
+        self.rects = rects.iter().map(SimpleRect::aabb).collect();
+        let count = self.rects.len();
+        if self.auto_cell && count > 0 {
+            let total_dim: f64 = self.rects.iter().map(|r| r.w + r.h).sum();
+            self.cell_size = ((total_dim / (count as f64 * 2.0)) * 1.5).clamp(50.0, 500.0);
+        }
+        self.rebuild_grid();
+    }
+
+    fn rebuild_grid(&mut self) {
+        self.grid.clear();
+        for (idx, r) in self.rects.iter().enumerate() {
+            let (gx_min, gx_max, gy_min, gy_max) = self.cell_span(r);
+            for gx in gx_min..=gx_max { for gy in gy_min..=gy_max {
+                self.grid.entry((gx, gy)).or_default().push(idx);
+            }}
+        }
+    }
+
+    /// Inclusive (gx_min, gx_max, gy_min, gy_max) cells a rect is bucketed in.
+    fn cell_span(&self, r: &SimpleRect) -> (i32, i32, i32, i32) {
+        ((r.x / self.cell_size).floor() as i32, ((r.x + r.w) / self.cell_size).floor() as i32,
+         (r.y / self.cell_size).floor() as i32, ((r.y + r.h) / self.cell_size).floor() as i32)
+    }
+
+    /// Re-bucket one rect: drop it from its old cells, insert into the new ones.
+    /// Buckets stay index-sorted, matching a full rebuild; `cell_size` is kept.
+    pub(crate) fn replace_rect(&mut self, index: usize, rect: SimpleRect) -> Result<(), String> {
+        let Some(old) = self.rects.get(index).copied() else {
+            return Err(format!("[layout] rect index {} out of range ({})", index, self.rects.len()));
+        };
+        let (ox0, ox1, oy0, oy1) = self.cell_span(&old);
+        for gx in ox0..=ox1 { for gy in oy0..=oy1 {
+            if let Some(b) = self.grid.get_mut(&(gx, gy)) {
+                b.retain(|&i| i != index);
+                if b.is_empty() { self.grid.remove(&(gx, gy)); }
+            }
+        }}
+        let rect = rect.aabb();
+        self.rects[index] = rect;
+        let (nx0, nx1, ny0, ny1) = self.cell_span(&rect);
+        for gx in nx0..=nx1 { for gy in ny0..=ny1 {
+            let b = self.grid.entry((gx, gy)).or_default();
+            if let Err(pos) = b.binary_search(&index) { b.insert(pos, index); }
+        }}
+        Ok(())
+    }
+
+    pub(crate) fn snap_held(&mut self, cx: f64, cy: f64, w: f64, h: f64, velocity: Option<f64>) -> SnapResult {
+        let k = velocity_scale(velocity);
+        let [thr_x, thr_y] = self.held.map(|held| k * if held { self.hold_threshold } else { self.capture_threshold });
+        let hit = self.snap(cx, cy, w, h, thr_x, thr_y);
+        self.held = [hit.snapped_x, hit.snapped_y];
+        hit.result
+    }
+
+    /// Rect indices bucketed in the cells covering (x, y, w, h) expanded per axis.
+    fn candidates(&self, x: f64, y: f64, w: f64, h: f64, thr_x: f64, thr_y: f64) -> Vec<usize> {
+        let gx_min = ((x - thr_x) / self.cell_size).floor() as i32;
+        let gx_max = ((x + w + thr_x) / self.cell_size).floor() as i32;
+        let gy_min = ((y - thr_y) / self.cell_size).floor() as i32;
+        let gy_max = ((y + h + thr_y) / self.cell_size).floor() as i32;
+
+        let mut seen = HashSet::new();
+        let mut cands: Vec<usize> = Vec::new();
+        for gx in gx_min..=gx_max { for gy in gy_min..=gy_max {
+            if let Some(idxs) = self.grid.get(&(gx, gy)) {
+                for &i in idxs { if seen.insert(i) { cands.push(i); } }
+            }
+        }}
+        // Hash-map order isn't stable across runs; every pass sees index order.
+        cands.sort_unstable();
+        cands
+    }
+
+    pub(crate) fn distances(&self, d: &SimpleRect) -> Distances {
+        let reach = DISTANCE_CELLS * self.cell_size;
+        let mut out = Distances::default();
+        let closer = |slot: &mut Option<f64>, idx: &mut Option<usize>, gap: f64, i: usize| {
+            if slot.is_none_or(|g| gap < g) { *slot = Some(gap); *idx = Some(i); }
+        };
+        for i in self.candidates(d.x, d.y, d.w, d.h, reach, reach) {
+            let s = &self.rects[i];
+            let rows = s.y < d.y + d.h && s.y + s.h > d.y;
+            let cols = s.x < d.x + d.w && s.x + s.w > d.x;
+            if rows && s.x + s.w <= d.x { closer(&mut out.left,  &mut out.left_index,  d.x - (s.x + s.w), i); }
+            if rows && s.x >= d.x + d.w { closer(&mut out.right, &mut out.right_index, s.x - (d.x + d.w), i); }
+            if cols && s.y + s.h <= d.y { closer(&mut out.up,    &mut out.up_index,    d.y - (s.y + s.h), i); }
+            if cols && s.y >= d.y + d.h { closer(&mut out.down,  &mut out.down_index,  s.y - (d.y + d.h), i); }
+        }
+        out
+    }
+
+    pub(crate) fn group_snap(&self, x: f64, y: f64, w: f64, h: f64, thr: f64) -> GroupSnapResult {
+        let r = self.snap(x, y, w, h, thr, thr).result;
+        GroupSnapResult { dx: r.x - x, dy: r.y - y, guides: r.guides }
+    }
+
+    /// Resize core: snap only the edges `handle` moves. Per edge the priority
+    /// is manual guide, nearest sibling edge/center, then canvas line; a snap
+    /// that would shrink the rect below 1px is skipped.
+    pub(crate) fn resize_snap(&self, x: f64, y: f64, w: f64, h: f64, handle: &str, thr: f64) -> Result<ResizeSnapResult, String> {
+        if handle.is_empty() || !handle.chars().all(|c| "nsew".contains(c)) {
+            return Err(format!("[layout] unknown resize handle \"{}\"", handle));
+        }
+        let cands = self.candidates(x, y, w, h, thr, thr);
+        let (mut x0, mut y0, mut x1, mut y1) = (x, y, x + w, y + h);
+        let mut guides: Vec<Guide> = Vec::new();
+        // (handle char, axis, moving-edge is the far edge)
+        for (c, axis, far) in [('w', 0, false), ('e', 0, true), ('n', 1, false), ('s', 1, true)] {
+            if !handle.contains(c) { continue; }
+            let edge = match (axis, far) { (0, false) => x0, (0, true) => x1, (_, false) => y0, _ => y1 };
+            let Some((pos, g)) = self.edge_snap(axis, edge, (x0, y0, x1, y1), &cands, thr) else { continue };
+            let ok = match (axis, far) { (0, false) => x1 - pos >= 1.0, (0, true) => pos - x0 >= 1.0, (_, false) => y1 - pos >= 1.0, _ => pos - y0 >= 1.0 };
+            if !ok { continue; }
+            match (axis, far) { (0, false) => x0 = pos, (0, true) => x1 = pos, (_, false) => y0 = pos, _ => y1 = pos }
+            guides.push(g);
+        }
+        Ok(ResizeSnapResult { x: x0, y: y0, width: x1 - x0, height: y1 - y0, guides })
+    }
+
+    /// Best snap line for one moving edge; `rect` is (x0, y0, x1, y1) for guide extents.
+    fn edge_snap(&self, axis: usize, edge: f64, rect: (f64, f64, f64, f64), cands: &[usize], thr: f64) -> Option<(f64, Guide)> {
+        let (orientation, c0, c1) = if axis == 0 { ("vertical", rect.1, rect.3) } else { ("horizontal", rect.0, rect.2) };
+        let guide = |pos: f64, start: f64, end: f64, t: &str| Guide {
+            orientation: orientation.into(), pos, start, end, guide_type: t.into(), gap_px: 0.0, distance: None,
+        };
+        if let Some((_, g)) = nearest_line(self.manual[axis].iter().copied(), edge, 0.0, thr) {
+            return Some((g, guide(g, c0, c1, "manual")));
+        }
+        let mut best: Option<(f64, f64, &SimpleRect)> = None;
+        for &i in cands {
+            let s = &self.rects[i];
+            let (s0, sl) = if axis == 0 { (s.x, s.w) } else { (s.y, s.h) };
+            for sv in [s0, s0 + sl / 2.0, s0 + sl] {
+                let d = (sv - edge).abs();
+                if d < thr && best.is_none_or(|b| d < b.0) { best = Some((d, sv, s)); }
+            }
+        }
+        if let Some((_, sv, s)) = best {
+            let (sc0, sc1) = if axis == 0 { (s.y, s.y + s.h) } else { (s.x, s.x + s.w) };
+            return Some((sv, guide(sv, c0.min(sc0), c1.max(sc1), "align")));
+        }
+        let c = self.canvas?;
+        let (s0, sl, cc0, cc1) = if axis == 0 { (c.x, c.w, c.y, c.y + c.h) } else { (c.y, c.h, c.x, c.x + c.w) };
+        nearest_line([s0, s0 + sl / 2.0, s0 + sl].into_iter(), edge, 0.0, thr)
+            .map(|(_, g)| (g, guide(g, cc0, cc1, "canvas")))
+    }
+
+    /// Snap core with independent x / y thresholds. Priority per axis:
+    /// manual guides, sibling alignment (closest wins, see `align_pick`),
+    /// canvas frame, spacing / equal-gap snaps, midpoints between adjacent
+    /// siblings, then the pixel grid.
+    /// The flags report which axes moved.
+    pub(crate) fn snap(&self, cx: f64, cy: f64, w: f64, h: f64, thr_x: f64, thr_y: f64) -> SnapHit {
+        self.snap_excluding(cx, cy, w, h, thr_x, thr_y, &[])
+    }
+
+    /// `snap` with the `exclude` rect indices dropped from the candidate set.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn snap_excluding(&self, cx: f64, cy: f64, w: f64, h: f64, thr_x: f64, thr_y: f64, exclude: &[usize]) -> SnapHit {
+        let mut nx = cx; let mut ny = cy;
+        let mut guides: Vec<Guide> = Vec::with_capacity(4);
+        let mut sx = false; let mut sy = false;
+        let mut mx: Option<usize> = None; let mut my: Option<usize> = None;
+
+        if let Some((d, g)) = self.manual_snap(0, nx, w, thr_x) {
+            nx += d; sx = true;
+            guides.push(Guide { orientation:"vertical".into(), pos:g, start:ny, end:ny+h, guide_type:"manual".into(), gap_px:0.0, distance:None });
+        }
+        if let Some((d, g)) = self.manual_snap(1, ny, h, thr_y) {
+            ny += d; sy = true;
+            guides.push(Guide { orientation:"horizontal".into(), pos:g, start:nx, end:nx+w, guide_type:"manual".into(), gap_px:0.0, distance:None });
+        }
+
+        let mut cands = self.candidates(cx, cy, w, h, thr_x, thr_y);
+        if !exclude.is_empty() { cands.retain(|i| !exclude.contains(i)); }
+
+        if !sx { if let Some((d, sv, idx)) = self.align_pick(&cands, nx, w, thr_x, false) {
+            let s = &self.rects[idx];
+            nx += d; sx = true; mx = Some(idx);
+            guides.push(Guide { orientation:"vertical".into(), pos:sv,
+                start:ny.min(s.y), end:(ny+h).max(s.y+s.h),
+                guide_type:"align".into(), gap_px:0.0, distance:None });
+        }}
+        if !sy { if let Some((d, sv, idx)) = self.align_pick(&cands, ny, h, thr_y, true) {
+            let s = &self.rects[idx];
+            ny += d; sy = true; my = Some(idx);
+            guides.push(Guide { orientation:"horizontal".into(), pos:sv,
+                start:nx.min(s.x), end:(nx+w).max(s.x+s.w),
+                guide_type:"align".into(), gap_px:0.0, distance:None });
+        }}
+
+        if let Some(c) = self.canvas {
+            if !sx { if let Some((d, g)) = self.canvas_snap(0, nx, w, thr_x) {
+                nx += d; sx = true;
+                guides.push(Guide { orientation:"vertical".into(), pos:g, start:c.y, end:c.y+c.h, guide_type:"canvas".into(), gap_px:0.0, distance:None });
+            }}
+            if !sy { if let Some((d, g)) = self.canvas_snap(1, ny, h, thr_y) {
+                ny += d; sy = true;
+                guides.push(Guide { orientation:"horizontal".into(), pos:g, start:c.x, end:c.x+c.w, guide_type:"canvas".into(), gap_px:0.0, distance:None });
+            }}
+        }
+
+        if !sx { if let Some(g) = self.spacing(&SimpleRect::new(nx, ny, w, h), &cands, thr_x, false) { nx = g.0; sx = true; guides.extend(g.1); } }
+        if !sy { if let Some(g) = self.spacing(&SimpleRect::new(nx, ny, w, h), &cands, thr_y, true) { ny = g.0; sy = true; guides.extend(g.1); } }
+        if !sx { if let Some(g) = self.gap_x(nx, w, &cands, thr_x) { nx = g.0; sx = true; guides.extend(g.1); } }
+        if !sy { if let Some(g) = self.gap_y(ny, h, &cands, thr_y) { ny = g.0; sy = true; guides.extend(g.1); } }
+        if !sx { if let Some(g) = self.midpoint(&SimpleRect::new(nx, ny, w, h), &cands, thr_x, false) { nx = g.0; sx = true; guides.push(g.1); } }
+        if !sy { if let Some(g) = self.midpoint(&SimpleRect::new(nx, ny, w, h), &cands, thr_y, true) { ny = g.0; sy = true; guides.push(g.1); } }
+
+        // Pixel grid: unconditional, but not reported in the snapped flags so
+        // `query_snapping_held` keeps its capture threshold for real targets.
+        let step = self.pixel_grid;
+        if step > 0.0 {
+            if !sx {
+                nx = (nx / step).round() * step;
+                guides.push(Guide { orientation:"vertical".into(), pos:nx, start:ny, end:ny+h, guide_type:"pixel-grid".into(), gap_px:0.0, distance:None });
+            }
+            if !sy {
+                ny = (ny / step).round() * step;
+                guides.push(Guide { orientation:"horizontal".into(), pos:ny, start:nx, end:nx+w, guide_type:"pixel-grid".into(), gap_px:0.0, distance:None });
+            }
+        }
+
+        SnapHit {
+            result: SnapResult { x:nx, y:ny, guides, matched_x_index: mx, matched_y_index: my },
+            snapped_x: sx, snapped_y: sy,
+        }
+    }
+
+    pub(crate) fn state(&self) -> EngineState {
+        EngineState {
+            version: ENGINE_STATE_VERSION, rects: self.rects.clone(), cell_size: self.cell_size,
+            capture_threshold: self.capture_threshold, hold_threshold: self.hold_threshold,
+            guides: self.manual_guides(), canvas_bounds: self.canvas, auto_cell_size: self.auto_cell,
+            pixel_grid: self.pixel_grid,
+        }
+    }
+
+    pub(crate) fn restore(&mut self, st: EngineState) -> Result<(), String> {
+        if st.version > ENGINE_STATE_VERSION {
+            return Err(format!("[layout] state version {} is newer than {}", st.version, ENGINE_STATE_VERSION));
+        }
+        if !(st.cell_size.is_finite() && st.cell_size > 0.0) {
+            return Err(format!("[layout] invalid cell_size {}", st.cell_size));
+        }
+        self.rects = st.rects.iter().map(SimpleRect::aabb).collect();
+        self.cell_size = st.cell_size;
+        self.capture_threshold = st.capture_threshold;
+        self.hold_threshold = st.hold_threshold;
+        self.held = [false; 2];
+        self.load_guides(st.guides);
+        self.canvas = st.canvas_bounds;
+        self.auto_cell = st.auto_cell_size;
+        self.pixel_grid = if st.pixel_grid.is_finite() && st.pixel_grid > 0.0 { st.pixel_grid } else { 0.0 };
+        self.rebuild_grid();
+        Ok(())
+    }
+
+    pub(crate) fn pin_cell_size(&mut self, size: f64) {
+        self.auto_cell = false;
+        self.cell_size = size;
+        self.rebuild_grid();
+    }
+
+    pub(crate) fn load_guides(&mut self, guides: Vec<(String, f64)>) {
+        self.manual = [Vec::new(), Vec::new()];
+        for (o, pos) in guides {
+            match o.as_str() { "vertical" => self.manual[0].push(pos), "horizontal" => self.manual[1].push(pos), _ => {} }
+        }
+    }
+
+    pub(crate) fn manual_guides(&self) -> Vec<(String, f64)> {
+        self.manual[0].iter().map(|&p| ("vertical".to_string(), p))
+            .chain(self.manual[1].iter().map(|&p| ("horizontal".to_string(), p))).collect()
+    }
+
+    /// Closest manual guide to the dragged edges/center on one axis:
+    /// (delta to apply, guide pos).
+    fn manual_snap(&self, axis: usize, pos: f64, len: f64, thr: f64) -> Option<(f64, f64)> {
+        nearest_line(self.manual[axis].iter().copied(), pos, len, thr)
+    }
+
+    /// Canvas start / center / end on one axis, as `manual_snap`.
+    fn canvas_snap(&self, axis: usize, pos: f64, len: f64, thr: f64) -> Option<(f64, f64)> {
+        let c = self.canvas?;
+        let (s, l) = if axis == 0 { (c.x, c.w) } else { (c.y, c.h) };
+        nearest_line([s, s + l / 2.0, s + l].into_iter(), pos, len, thr)
+    }
+
+    pub(crate) fn content_bbox(&self) -> Option<BBox> {
+        let first = self.rects.first()?;
+        let (mut x0, mut y0, mut x1, mut y1) = (first.x, first.y, first.x + first.w, first.y + first.h);
+        for r in &self.rects[1..] {
+            x0 = x0.min(r.x); y0 = y0.min(r.y);
+            x1 = x1.max(r.x + r.w); y1 = y1.max(r.y + r.h);
+        }
+        Some(BBox { x: x0, y: y0, w: x1 - x0, h: y1 - y0 })
+    }
+
+    pub(crate) fn cell_info(&self, x: f64, y: f64) -> CellInfo {
+        let (gx, gy) = ((x / self.cell_size).floor() as i32, (y / self.cell_size).floor() as i32);
+        CellInfo { gx, gy, indices: self.grid.get(&(gx, gy)).cloned().unwrap_or_default() }
+    }
+
     fn gap_x(&self, dx:f64, dw:f64, cands:&[usize], thr:f64) -> Option<(f64, Vec<Guide>)> {
         let mut ls:Option<&SimpleRect>=None; let mut ld=f64::MAX;
         let mut rs:Option<&SimpleRect>=None; let mut rd=f64::MAX;
@@ -186,11 +712,102 @@ impl LayoutEngine {
         if delta.abs()>thr { return None; }
         let sx=dx+delta; let top=l.y.min(r.y); let bot=(l.y+l.h).max(r.y+r.h); let mid=(top+bot)/2.0;
         Some((sx, vec![
-            Guide{orientation:"vertical".into(),pos:sx,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round()},
-            Guide{orientation:"vertical".into(),pos:sx+dw,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round()},
+            Guide{orientation:"vertical".into(),pos:sx,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round(),distance:Some(gap)},
+            Guide{orientation:"vertical".into(),pos:sx+dw,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round(),distance:Some(gap)},
         ]))
     }
 
+    /// Figma-style equal spacing on one axis (`y_axis` false → x). Reference gaps
+    /// are between adjacent candidates in the dragged rect's row/column; the
+    /// dragged rect snaps so its gap to the nearest neighbour equals one of
+    /// them, but only if at least `SPACING_MIN_REFS` references share that gap.
+    /// Returns the snapped main-axis position plus one `spacing` guide per equal gap.
+    fn spacing(&self, drag:&SimpleRect, cands:&[usize], thr:f64, y_axis:bool) -> Option<(f64, Vec<Guide>)> {
+        let d = Span::of(drag, y_axis);
+        let peers: Vec<Span> = cands.iter().map(|&i| Span::of(&self.rects[i], y_axis))
+            .filter(|p| p.cross_overlaps(&d)).collect();
+        // (gap, from, to, cross mid)
+        let mut refs: Vec<(f64,f64,f64,f64)> = Vec::new();
+        for a in &peers {
+            let next = peers.iter().filter(|b| b.s >= a.end() && b.cross_overlaps(a))
+                .min_by(|x,y| x.s.partial_cmp(&y.s).unwrap_or(std::cmp::Ordering::Equal));
+            if let Some(b) = next { if b.s > a.end() { refs.push((b.s-a.end(), a.end(), b.s, a.cross_mid(b))); } }
+        }
+        let before = peers.iter().filter(|p| p.end() <= d.s+thr).max_by(|x,y| x.end().partial_cmp(&y.end()).unwrap_or(std::cmp::Ordering::Equal));
+        let after  = peers.iter().filter(|p| p.s >= d.end()-thr).min_by(|x,y| x.s.partial_cmp(&y.s).unwrap_or(std::cmp::Ordering::Equal));
+        // (|delta|, snapped pos, gap, neighbour)
+        let mut best: Option<(f64,f64,f64,&Span)> = None;
+        for &(g,..) in &refs {
+            if refs.iter().filter(|r| (r.0-g).abs() <= SPACING_TOL).count() < SPACING_MIN_REFS { continue; }
+            for (nb, target) in [(before, before.map(|b| b.end()+g)), (after, after.map(|a| a.s-g-d.l))] {
+                let (Some(nb), Some(t)) = (nb, target) else { continue };
+                let dist = (t-d.s).abs();
+                if dist < thr && best.is_none_or(|b| dist < b.0) { best = Some((dist, t, g, nb)); }
+            }
+        }
+        let (_, snapped, g, nb) = best?;
+        let orientation = if y_axis { "vertical" } else { "horizontal" };
+        let guide = |from:f64, to:f64, mid:f64| Guide { orientation: orientation.into(), pos: mid, start: from, end: to,
+            guide_type: "spacing".into(), gap_px: g.round(), distance: Some(g) };
+        let mut guides: Vec<Guide> = refs.iter().filter(|r| (r.0-g).abs() <= SPACING_TOL).map(|r| guide(r.1, r.2, r.3)).collect();
+        let (from,to) = if nb.s < snapped { (nb.end(), snapped) } else { (snapped+d.l, nb.s) };
+        guides.push(guide(from, to, nb.cross_mid(&d)));
+        Some((snapped, guides))
+    }
+
+    /// Center the dragged rect on the midpoint of the space between two
+    /// adjacent candidates (`y_axis` false → x). Adjacent = `b` is the nearest
+    /// rect starting after `a` ends and the two share a row/column; the
+    /// dragged rect itself need not sit between them (`gap_x`/`gap_y` cover
+    /// that case first). Returns the snapped main-axis position and a
+    /// `midpoint` guide spanning all three.
+    fn midpoint(&self, drag: &SimpleRect, cands: &[usize], thr: f64, y_axis: bool) -> Option<(f64, Guide)> {
+        let d = Span::of(drag, y_axis);
+        let spans: Vec<Span> = cands.iter().map(|&i| Span::of(&self.rects[i], y_axis)).collect();
+        // (|delta|, snapped pos, midpoint, cross start, cross end)
+        let mut best: Option<(f64, f64, f64, f64, f64)> = None;
+        for a in &spans {
+            let next = spans.iter().filter(|b| b.s >= a.end())
+                .min_by(|x, y| x.s.partial_cmp(&y.s).unwrap_or(std::cmp::Ordering::Equal));
+            let Some(b) = next.filter(|b| b.s > a.end() && b.cross_overlaps(a)) else { continue };
+            let m = (a.end() + b.s) / 2.0;
+            let t = m - d.l / 2.0;
+            let dist = (t - d.s).abs();
+            if dist < thr && best.is_none_or(|x| dist < x.0) {
+                let c0 = a.c.min(b.c).min(d.c);
+                let c1 = (a.c + a.cl).max(b.c + b.cl).max(d.c + d.cl);
+                best = Some((dist, t, m, c0, c1));
+            }
+        }
+        let (_, t, m, c0, c1) = best?;
+        Some((t, Guide { orientation: if y_axis { "horizontal" } else { "vertical" }.into(), pos: m, start: c0, end: c1,
+            guide_type: "midpoint".into(), gap_px: 0.0, distance: None }))
+    }
+
+    /// Closest sibling alignment on one axis (`y_axis` false → x): every
+    /// in-threshold pairing of the dragged start / center / end with a
+    /// candidate's start / center / end is considered, and the smallest
+    /// |delta| wins; ties go to the lower rect index, then the earlier
+    /// pairing. Returns `(delta, snapped line, rect index)`.
+    fn align_pick(&self, cands: &[usize], pos: f64, len: f64, thr: f64, y_axis: bool) -> Option<(f64, f64, usize)> {
+        let mut best: Option<(f64, f64, usize)> = None;
+        for &idx in cands {
+            let r = Span::of(&self.rects[idx], y_axis);
+            for t in [pos, pos + len / 2.0, pos + len] {
+                for sv in [r.s, r.s + r.l / 2.0, r.end()] {
+                    let d = sv - t;
+                    if d.abs() >= thr { continue; }
+                    let better = match best {
+                        None => true,
+                        Some((bd, _, bi)) => d.abs() < bd.abs() || (d.abs() == bd.abs() && idx < bi),
+                    };
+                    if better { best = Some((d, sv, idx)); }
+                }
+            }
+        }
+        best
+    }
+
     fn gap_y(&self, dy:f64, dh:f64, cands:&[usize], thr:f64) -> Option<(f64, Vec<Guide>)> {
         let mut ts:Option<&SimpleRect>=None; let mut td=f64::MAX;
         let mut bs:Option<&SimpleRect>=None; let mut bd=f64::MAX;
@@ -204,12 +821,128 @@ impl LayoutEngine {
         if delta.abs()>thr { return None; }
         let sy=dy+delta; let lft=t.x.min(b.x); let rgt=(t.x+t.w).max(b.x+b.w); let mid=(lft+rgt)/2.0;
         Some((sy, vec![
-            Guide{orientation:"horizontal".into(),pos:sy,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round()},
-            Guide{orientation:"horizontal".into(),pos:sy+dh,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round()},
+            Guide{orientation:"horizontal".into(),pos:sy,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round(),distance:Some(gap)},
+            Guide{orientation:"horizontal".into(),pos:sy+dh,start:mid-8.0,end:mid+8.0,guide_type:"gap".into(),gap_px:gap.round(),distance:Some(gap)},
         ]))
     }
 }
 
+/// Nearest of `lines` to a span's start/center/end within `thr`: (delta, line).
+fn nearest_line(lines: impl Iterator<Item = f64>, pos: f64, len: f64, thr: f64) -> Option<(f64, f64)> {
+    let mut best: Option<(f64, f64)> = None;
+    for g in lines {
+        for t in [pos, pos + len / 2.0, pos + len] {
+            let d = g - t;
+            if d.abs() < thr && best.is_none_or(|b| d.abs() < b.0.abs()) { best = Some((d, g)); }
+        }
+    }
+    best
+}
+
+/// Convert a `SnapResult`'s guides to SVG `d` strings.
+/// Vertical → `M pos start V end`, horizontal → `M start pos H end`.
+#[wasm_bindgen]
+pub fn guides_to_svg_paths(result_val: JsValue) -> Result<JsValue, JsValue> {
+    let result: SnapResult = serde_wasm_bindgen::from_value(result_val)?;
+    Ok(serde_wasm_bindgen::to_value(&guide_paths(&result.guides))?)
+}
+
+pub(crate) fn guide_paths(guides: &[Guide]) -> Vec<GuidePath> {
+    guides.iter().map(|g| GuidePath {
+        d: if g.orientation == "vertical" { format!("M{} {}V{}", g.pos, g.start, g.end) }
+           else { format!("M{} {}H{}", g.start, g.pos, g.end) },
+        guide_type: g.guide_type.clone(),
+    }).collect()
+}
+
+/// Guides closer than this (px) on the same axis are treated as one line.
+const GUIDE_MERGE_TOL: f64 = 0.5;
+
+/// Merge collinear guides in a `SnapResult` (e.g. from several dragged
+/// elements) into one guide spanning the union of their extents.
+#[wasm_bindgen]
+pub fn merge_collinear_guides(result_val: JsValue) -> Result<JsValue, JsValue> {
+    let mut result: SnapResult = serde_wasm_bindgen::from_value(result_val)?;
+    result.guides = merge_guides(result.guides, GUIDE_MERGE_TOL);
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+/// Same orientation + type and `pos` within `tol` → one guide, first-seen order.
+/// `gap` guides are short measurement ticks and are never merged.
+pub(crate) fn merge_guides(guides: Vec<Guide>, tol: f64) -> Vec<Guide> {
+    let mut out: Vec<Guide> = Vec::with_capacity(guides.len());
+    for g in guides {
+        let hit = (g.guide_type != "gap").then(|| out.iter_mut().find(|o| {
+            o.guide_type == g.guide_type && o.orientation == g.orientation && (o.pos - g.pos).abs() <= tol
+        })).flatten();
+        match hit {
+            Some(o) => { o.start = o.start.min(g.start); o.end = o.end.max(g.end); }
+            None => out.push(g),
+        }
+    }
+    out
+}
+
+/// Toolbar align / distribute over a selection. `mode` is `left`, `center`,
+/// `right`, `top`, `middle`, `bottom` (against the selection's bounding box)
+/// or `distribute-horizontal` / `distribute-vertical`, which keep the two
+/// outermost rects fixed and equalize the gaps between all of them. Returns
+/// the rects in input order with only `x` / `y` moved; rotated rects align
+/// by their bounding box.
+#[wasm_bindgen]
+pub fn align_nodes(rects_json: String, mode: String) -> Result<String, JsValue> {
+    let rects: Vec<SimpleRect> = serde_json::from_str(&rects_json)
+        .map_err(|e| JsValue::from_str(&format!("[align] parse: {}", e)))?;
+    let out = aligned(rects, &mode).map_err(|e| JsValue::from_str(&e))?;
+    serde_json::to_string(&out).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+pub(crate) fn aligned(mut rects: Vec<SimpleRect>, mode: &str) -> Result<Vec<SimpleRect>, String> {
+    if rects.is_empty() { return Ok(rects); }
+    let boxes: Vec<SimpleRect> = rects.iter().map(|r| r.aabb()).collect();
+    let x0 = boxes.iter().map(|b| b.x).fold(f64::MAX, f64::min);
+    let y0 = boxes.iter().map(|b| b.y).fold(f64::MAX, f64::min);
+    let x1 = boxes.iter().map(|b| b.x + b.w).fold(f64::MIN, f64::max);
+    let y1 = boxes.iter().map(|b| b.y + b.h).fold(f64::MIN, f64::max);
+    if mode == "distribute-horizontal" || mode == "distribute-vertical" {
+        distribute(&mut rects, &boxes, if mode == "distribute-horizontal" { 0 } else { 1 });
+        return Ok(rects);
+    }
+    // (dx, dy) moving the rect's bbox onto the alignment line
+    let shift = |b: &SimpleRect| -> Option<(f64, f64)> { Some(match mode {
+        "left"   => (x0 - b.x, 0.0),
+        "center" => ((x0 + x1 - b.w) / 2.0 - b.x, 0.0),
+        "right"  => (x1 - b.w - b.x, 0.0),
+        "top"    => (0.0, y0 - b.y),
+        "middle" => (0.0, (y0 + y1 - b.h) / 2.0 - b.y),
+        "bottom" => (0.0, y1 - b.h - b.y),
+        _ => return None,
+    }) };
+    for (r, b) in rects.iter_mut().zip(&boxes) {
+        let (dx, dy) = shift(b).ok_or_else(|| format!("[align] unknown mode: {}", mode))?;
+        r.x += dx; r.y += dy;
+    }
+    Ok(rects)
+}
+
+/// Equal gaps along `axis` between the first and last rect (by bbox start);
+/// under three rects there is nothing to move.
+fn distribute(rects: &mut [SimpleRect], boxes: &[SimpleRect], axis: usize) {
+    if rects.len() < 3 { return; }
+    let span = |b: &SimpleRect| if axis == 0 { (b.x, b.w) } else { (b.y, b.h) };
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by(|&a, &b| span(&boxes[a]).0.partial_cmp(&span(&boxes[b]).0).unwrap_or(std::cmp::Ordering::Equal));
+    let (first, last) = (span(&boxes[order[0]]), span(&boxes[order[order.len() - 1]]));
+    let total: f64 = order.iter().map(|&i| span(&boxes[i]).1).sum();
+    let gap = (last.0 + last.1 - first.0 - total) / (order.len() - 1) as f64;
+    let mut at = first.0;
+    for &i in &order {
+        let (p, l) = span(&boxes[i]);
+        if axis == 0 { rects[i].x += at - p; } else { rects[i].y += at - p; }
+        at += l + gap;
+    }
+}
+
 // ── §6 absolute_to_grid ───────────────────────────────────────────────────────
 
 const SNAP_TOL: f64 = 4.0;
@@ -221,6 +954,17 @@ pub struct GridInputNode { pub id:String, pub x:f64, pub y:f64, pub w:f64, pub h
 pub struct GridItem {
     pub id:String, pub col_start:usize, pub col_end:usize,
     pub row_start:usize, pub row_end:usize,
+    /// Placement+size fingerprint (opt-in via `hashItems`). Masked to 53 bits
+    /// so it survives `JSON.parse` as an exact JS number.
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub hash:Option<u64>,
+    /// Stacking suggestion (1-based input order), set only on items that
+    /// share cells with another item.
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub z_index:Option<usize>,
+    /// `grid-area` name when `namedAreas` produced a template.
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub area:Option<String>,
 }
 
 #[derive(Serialize,Deserialize)] #[serde(rename_all="camelCase")]
@@ -229,15 +973,80 @@ pub struct GridLayout {
     pub fr_columns:String,       pub fr_rows:String,
     pub col_widths_px:Vec<f64>,  pub row_heights_px:Vec<f64>,
     pub items:Vec<GridItem>,
+    /// CSS `gap` ("row col", or one value when equal) when uniform gutters
+    /// were collapsed out of the tracks.
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub gap:Option<String>,
+    /// Id pairs whose cell ranges intersect — layering grid can't express.
+    #[serde(default, skip_serializing_if="Vec::is_empty")]
+    pub overlaps:Vec<(String, String)>,
+    /// `grid-template-areas` value (`"a b" "c ."`) when `namedAreas` is set
+    /// and every item owns a distinct rectangle. None → use line numbers.
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub grid_template_areas:Option<String>,
+    /// Canvas position of grid line 1 on each axis, so `update_grid_item`
+    /// can map node coordinates back onto the tracks. Older layouts → (0, 0).
+    #[serde(default)]
+    pub origin:(f64, f64),
+    /// 1-based `(col, row)` cells no item covers, row by row — candidates
+    /// for merging tracks. Explicit gutter tracks show up here too.
+    #[serde(default, skip_serializing_if="Vec::is_empty")]
+    pub empty_cells:Vec<(usize, usize)>,
+    /// Options the layout was built with, carried so `update_grid_item`
+    /// re-places with the same snapTol / precision / templates. None on
+    /// older layouts, which fall back to recovering them from the output.
+    #[serde(default)]
+    pub options:Option<GridOptions>,
+    /// `canvas_width` passed to `absolute_to_grid*` (0 → the grid's own width).
+    #[serde(default)]
+    pub canvas_width:f64,
 }
 
-fn dedup_coords(mut c: Vec<f64>) -> Vec<f64> {
+/// Optional knobs for `absolute_to_grid_with`. Missing keys take defaults.
+#[derive(Serialize,Deserialize,Clone)] #[serde(rename_all="camelCase", default)]
+pub struct GridOptions {
+    /// Stamp each item with `hash` so live-CSS callers can skip unchanged items.
+    pub hash_items:bool,
+    /// Decimal places for non-px track tokens (trailing zeros trimmed).
+    pub precision:usize,
+    /// Put the fr tracks in `templateColumns`/`templateRows` too, for callers
+    /// that apply the template verbatim. px sizes stay in `colWidthsPx`/`rowHeightsPx`.
+    pub use_fr:bool,
+    /// px distance under which breakpoints merge into one grid line (and
+    /// gutters count as equal). Lower it for sub-pixel canvases, raise it
+    /// for coarse touch input. Must be >= 0.
+    pub snap_tol:f64,
+    /// Emit `minmax()` templates that stretch with the container: each
+    /// content track is `minmax(0, Npx)` except the largest (first on ties),
+    /// which is `minmax(Npx, 1fr)`. Gutter tracks stay fixed. Wins over `useFr`.
+    pub responsive:bool,
+    /// Name each item's region after its id and emit `gridTemplateAreas`.
+    /// Skipped (numeric placement only) when items overlap or one is empty.
+    pub named_areas:bool,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self { GridOptions { hash_items: false, precision: 3, use_fr: false, snap_tol: SNAP_TOL, responsive: false, named_areas: false } }
+}
+
+/// `v` rounded to `prec` decimals with trailing zeros (and a bare ".") trimmed:
+/// 0.33333 → "0.333", 1.0 → "1", 0.5 → "0.5".
+pub(crate) fn fmt_decimal(v: f64, prec: usize) -> String {
+    let s = format!("{:.*}", prec, v);
+    let s = if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.') } else { &s };
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+/// Sorted, merged breakpoints. Sign-agnostic: coordinates left of / above the
+/// origin sort and average like any others, so track sizes (adjacent
+/// differences) stay positive when breakpoints straddle zero.
+fn dedup_coords(mut c: Vec<f64>, tol: f64) -> Vec<f64> {
     if c.is_empty() { return c; }
     c.sort_by(|a,b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
     let mut res = Vec::with_capacity(c.len());
     let mut sum = c[0]; let mut cnt = 1usize;
     for &v in &c[1..] {
-        if (v - sum/cnt as f64).abs() <= SNAP_TOL { sum += v; cnt += 1; }
+        if (v - sum/cnt as f64).abs() <= tol { sum += v; cnt += 1; }
         else { res.push(sum/cnt as f64); sum = v; cnt = 1; }
     }
     res.push(sum/cnt as f64);
@@ -251,32 +1060,911 @@ fn find_idx(breaks: &[f64], target: f64) -> usize {
         .map(|(i,_)| i).unwrap_or(0)
 }
 
-#[wasm_bindgen]
-pub fn absolute_to_grid(nodes_json: String, canvas_width: f64) -> Result<String, JsValue> {
-    let nodes: Vec<GridInputNode> = serde_json::from_str(&nodes_json)
-        .map_err(|e| JsValue::from_str(&format!("[grid] parse: {}", e)))?;
-    if nodes.is_empty() { return Err(JsValue::from_str("[grid] no nodes")); }
+/// Sum of the px tracks between 1-based grid lines `start` and `end`.
+fn span_px(tracks: &[f64], start: usize, end: usize) -> f64 {
+    tracks.get(start.min(end).saturating_sub(1)..start.max(end).saturating_sub(1))
+        .map(|t| t.iter().sum()).unwrap_or(0.0)
+}
+
+/// Largest integer fr ratio still considered "clean"; beyond it the
+/// decimal form reads better than e.g. `317fr 523fr`.
+const MAX_FR_RATIO: u64 = 24;
+
+fn gcd(a: u64, b: u64) -> u64 { if b == 0 { a } else { gcd(b, a % b) } }
+
+/// px tracks → whole-number fr ratios via the GCD of the rounded sizes
+/// (120/240/120 → [1, 2, 1]). None when the reduced ratios are coprime
+/// and large (any above `MAX_FR_RATIO`), so callers fall back to decimals.
+pub(crate) fn fr_ratios(tracks: &[f64]) -> Option<Vec<u64>> {
+    let px: Vec<u64> = tracks.iter().map(|t| t.round().max(1.0) as u64).collect();
+    let g = px.iter().copied().reduce(gcd)?;
+    let ratios: Vec<u64> = px.iter().map(|p| p / g).collect();
+    ratios.iter().all(|&r| r <= MAX_FR_RATIO).then_some(ratios)
+}
+
+fn fr_join(r: &[u64]) -> String {
+    r.iter().map(|v| format!("{}fr", v)).collect::<Vec<_>>().join(" ")
+}
+
+/// `used[t]` is true when some `[start, end)` span covers track `t`.
+fn covered_tracks(n: usize, spans: &[(usize, usize)]) -> Vec<bool> {
+    let mut used = vec![false; n];
+    for &(s, e) in spans {
+        for u in used.iter_mut().take(e.saturating_sub(1)).skip(s.saturating_sub(1)) { *u = true; }
+    }
+    used
+}
+
+/// px track list for `responsive` grids: content tracks become
+/// `minmax(0, Npx)` and the largest content track (first on ties) becomes
+/// `minmax(Npx, 1fr)`, the one that absorbs extra width. Uncovered gutter
+/// tracks stay fixed `Npx`.
+fn minmax_tracks(tracks: &[f64], spans: &[(usize, usize)]) -> String {
+    let used = covered_tracks(tracks.len(), spans);
+    let flex = (0..tracks.len()).filter(|&i| used[i])
+        .fold(None, |best: Option<usize>, i| match best { Some(b) if tracks[b] >= tracks[i] => Some(b), _ => Some(i) });
+    tracks.iter().enumerate().map(|(i, &t)| match (used[i], Some(i) == flex) {
+        (true, true)  => format!("minmax({}px, 1fr)", t as i64),
+        (true, false) => format!("minmax(0, {}px)", t as i64),
+        _             => format!("{}px", t as i64),
+    }).collect::<Vec<_>>().join(" ")
+}
+
+/// Gutter width when `tracks` strictly alternate content / empty / content …
+/// with every empty track within `tol` of the first. `spans` are the
+/// items' 1-based `[start, end)` lines on this axis; a track is empty when no
+/// span covers it. None means irregular — keep the explicit gutter tracks.
+fn uniform_gutter(tracks: &[f64], spans: &[(usize, usize)], tol: f64) -> Option<f64> {
+    if tracks.len() < 3 || tracks.len().is_multiple_of(2) { return None; }
+    let used = covered_tracks(tracks.len(), spans);
+    if used.iter().enumerate().any(|(i, &u)| u != (i % 2 == 0)) { return None; }
+    let gutters: Vec<f64> = tracks.iter().skip(1).step_by(2).copied().collect();
+    let first = gutters[0];
+    gutters.iter().all(|g| (g - first).abs() <= tol)
+        .then(|| (gutters.iter().sum::<f64>() / gutters.len() as f64).round())
+}
+
+/// Drops the odd (gutter) tracks. Line numbers map as `L / 2 + 1`: content
+/// start lines 1, 3, 5 → 1, 2, 3 and end lines 2, 4, 6 → 2, 3, 4.
+fn drop_gutters(tracks: &mut Vec<f64>, lines: &mut [&mut usize]) {
+    *tracks = tracks.iter().step_by(2).copied().collect();
+    for l in lines.iter_mut() { **l = **l / 2 + 1; }
+}
+
+/// Pairs of items sharing at least one cell (half-open line ranges intersect
+/// on both axes), in input order. Every item in a pair gets `z_index` = its
+/// 1-based input position so later nodes keep painting on top.
+fn mark_overlaps(items: &mut [GridItem]) -> Vec<(String, String)> {
+    let hits = |a: (usize, usize), b: (usize, usize)| a.0 < b.1 && b.0 < a.1;
+    let mut pairs = Vec::new();
+    let mut stacked = vec![false; items.len()];
+    for i in 0..items.len() {
+        for j in i+1..items.len() {
+            let (a, b) = (&items[i], &items[j]);
+            if hits((a.col_start, a.col_end), (b.col_start, b.col_end))
+                && hits((a.row_start, a.row_end), (b.row_start, b.row_end)) {
+                pairs.push((a.id.clone(), b.id.clone()));
+                stacked[i] = true; stacked[j] = true;
+            }
+        }
+    }
+    for (i, it) in items.iter_mut().enumerate() {
+        if stacked[i] { it.z_index = Some(i + 1); }
+    }
+    pairs
+}
+
+/// Cells of a cols × rows grid outside every item's
+/// `[col_start, col_end) × [row_start, row_end)`, row-major.
+fn empty_cells(items: &[GridItem], cols: usize, rows: usize) -> Vec<(usize, usize)> {
+    let mut used = vec![false; cols * rows];
+    for it in items {
+        for r in it.row_start.max(1)..it.row_end.min(rows + 1) {
+            for c in it.col_start.max(1)..it.col_end.min(cols + 1) { used[(r - 1) * cols + c - 1] = true; }
+        }
+    }
+    (0..cols * rows).filter(|&i| !used[i]).map(|i| (i % cols + 1, i / cols + 1)).collect()
+}
+
+/// CSS `<custom-ident>` from a node id: chars outside `[A-Za-z0-9_-]` become
+/// `-`, and a leading digit or dash gets an `a-` prefix.
+fn area_ident(id: &str) -> String {
+    let s: String = id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' }).collect();
+    match s.chars().next() {
+        None => "area".into(),
+        Some(c) if c.is_ascii_digit() || c == '-' => format!("a-{}", s),
+        _ => s,
+    }
+}
+
+/// Fills a rows × cols name matrix (`.` = empty) and sets each item's `area`.
+/// Names that collide after sanitizing get a `-2`, `-3` … suffix. Callers
+/// rule out overlaps first; an empty span also aborts (leaving areas unset).
+fn template_areas(items: &mut [GridItem], cols: usize, rows: usize) -> Option<String> {
+    if items.iter().any(|i| i.col_end <= i.col_start || i.row_end <= i.row_start) { return None; }
+    let mut cells = vec![vec![".".to_string(); cols]; rows];
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut names = Vec::with_capacity(items.len());
+    for it in items.iter() {
+        let base = area_ident(&it.id);
+        let mut name = base.clone();
+        let mut k = 2;
+        while !taken.insert(name.clone()) { name = format!("{}-{}", base, k); k += 1; }
+        for row in cells.iter_mut().take(it.row_end - 1).skip(it.row_start - 1) {
+            for c in row.iter_mut().take(it.col_end - 1).skip(it.col_start - 1) { *c = name.clone(); }
+        }
+        names.push(name);
+    }
+    for (it, n) in items.iter_mut().zip(names) { it.area = Some(n); }
+    Some(cells.iter().map(|r| format!("\"{}\"", r.join(" "))).collect::<Vec<_>>().join(" "))
+}
+
+fn item_hash(it: &GridItem, cw: &[f64], rh: &[f64]) -> u64 {
+    let w = span_px(cw, it.col_start, it.col_end);
+    let h = span_px(rh, it.row_start, it.row_end);
+    let key = format!("{}|{}/{}|{}/{}|{}x{}", it.id, it.col_start, it.col_end, it.row_start, it.row_end, w, h);
+    fnv1a(&key) & ((1u64 << 53) - 1)
+}
+
+fn grid_layout(nodes: &[GridInputNode], canvas_width: f64, opts: &GridOptions) -> Result<GridLayout, String> {
+    if nodes.is_empty() { return Err("[grid] no nodes".into()); }
+    if opts.snap_tol.is_nan() || opts.snap_tol < 0.0 { return Err(format!("[grid] snapTol must be >= 0, got {}", opts.snap_tol)); }
+    // NaN would poison the breakpoint sort and nearest-line lookup.
+    if let Some(n) = nodes.iter().find(|n| ![n.x, n.y, n.w, n.h].iter().all(|v| v.is_finite())) {
+        return Err(format!("[grid] non-finite geometry on node {}", n.id));
+    }
     let mut xr = Vec::with_capacity(nodes.len()*2);
     let mut yr = Vec::with_capacity(nodes.len()*2);
-    for n in &nodes { xr.push(n.x); xr.push(n.x+n.w); yr.push(n.y); yr.push(n.y+n.h); }
-    let xb = dedup_coords(xr); let yb = dedup_coords(yr);
-    if xb.len()<2 || yb.len()<2 { return Err(JsValue::from_str("[grid] degenerate")); }
+    for n in nodes { xr.push(n.x); xr.push(n.x+n.w); yr.push(n.y); yr.push(n.y+n.h); }
+    let xb = dedup_coords(xr, opts.snap_tol); let yb = dedup_coords(yr, opts.snap_tol);
+    if xb.len()<2 || yb.len()<2 { return Err("[grid] degenerate".into()); }
     let cw: Vec<f64> = xb.windows(2).map(|w| (w[1]-w[0]).round().max(1.0)).collect();
     let rh: Vec<f64> = yb.windows(2).map(|w| (w[1]-w[0]).round().max(1.0)).collect();
-    let cw_sum: f64 = cw.iter().sum(); let rh_sum: f64 = rh.iter().sum();
-    let cw_base = if canvas_width > 0.0 { canvas_width } else { cw_sum };
-    let tc = cw.iter().map(|&w| format!("{}px", w as i64)).collect::<Vec<_>>().join(" ");
-    let tr = rh.iter().map(|&h| format!("{}px", h as i64)).collect::<Vec<_>>().join(" ");
-    let fc = cw.iter().map(|&w| format!("{:.2}fr", w/cw_base)).collect::<Vec<_>>().join(" ");
-    let fr = rh.iter().map(|&h| format!("{:.2}fr", h/rh_sum.max(1.0))).collect::<Vec<_>>().join(" ");
     let items: Vec<GridItem> = nodes.iter().map(|n| GridItem {
         id: n.id.clone(),
         col_start: find_idx(&xb, n.x)+1,   col_end: find_idx(&xb, n.x+n.w)+1,
         row_start: find_idx(&yb, n.y)+1,   row_end: find_idx(&yb, n.y+n.h)+1,
+        hash: None, z_index: None, area: None,
     }).collect();
-    serde_json::to_string(&GridLayout {
+    Ok(finish_grid(cw, rh, items, (xb[0], yb[0]), canvas_width, opts, true))
+}
+
+/// Everything after line placement: gutter collapse (when `collapse`), fr
+/// strings, overlaps, areas, hashes and the final templates. `cw` / `rh`
+/// are explicit px tracks with gutters still in, `items` on their lines.
+fn finish_grid(mut cw: Vec<f64>, mut rh: Vec<f64>, mut items: Vec<GridItem>, origin: (f64, f64),
+               canvas_width: f64, opts: &GridOptions, collapse: bool) -> GridLayout {
+    let col_gap = if !collapse { None } else {
+        uniform_gutter(&cw, &items.iter().map(|i| (i.col_start, i.col_end)).collect::<Vec<_>>(), opts.snap_tol)
+    };
+    let row_gap = if !collapse { None } else {
+        uniform_gutter(&rh, &items.iter().map(|i| (i.row_start, i.row_end)).collect::<Vec<_>>(), opts.snap_tol)
+    };
+    if col_gap.is_some() {
+        let mut lines: Vec<&mut usize> = items.iter_mut().flat_map(|i| [&mut i.col_start, &mut i.col_end]).collect();
+        drop_gutters(&mut cw, &mut lines);
+    }
+    if row_gap.is_some() {
+        let mut lines: Vec<&mut usize> = items.iter_mut().flat_map(|i| [&mut i.row_start, &mut i.row_end]).collect();
+        drop_gutters(&mut rh, &mut lines);
+    }
+    let gap = match (row_gap, col_gap) {
+        (None, None) => None,
+        (r, c) if r == c => r.map(|g| format!("{}px", g as i64)),
+        (r, c) => Some(format!("{}px {}px", r.unwrap_or(0.0) as i64, c.unwrap_or(0.0) as i64)),
+    };
+    let cw_sum: f64 = cw.iter().sum(); let rh_sum: f64 = rh.iter().sum();
+    let cw_base = if canvas_width > 0.0 { canvas_width } else { cw_sum };
+    let tc = cw.iter().map(|&w| format!("{}px", w as i64)).collect::<Vec<_>>().join(" ");
+    let tr = rh.iter().map(|&h| format!("{}px", h as i64)).collect::<Vec<_>>().join(" ");
+    let fc = fr_ratios(&cw).map(|r| fr_join(&r))
+        .unwrap_or_else(|| cw.iter().map(|&w| format!("{}fr", fmt_decimal(w/cw_base, opts.precision))).collect::<Vec<_>>().join(" "));
+    let fr = fr_ratios(&rh).map(|r| fr_join(&r))
+        .unwrap_or_else(|| rh.iter().map(|&h| format!("{}fr", fmt_decimal(h/rh_sum.max(1.0), opts.precision))).collect::<Vec<_>>().join(" "));
+    for it in &mut items { it.z_index = None; it.area = None; it.hash = None; }
+    let empty_cells = empty_cells(&items, cw.len(), rh.len());
+    let overlaps = mark_overlaps(&mut items);
+    let grid_template_areas = if opts.named_areas && overlaps.is_empty() {
+        template_areas(&mut items, cw.len(), rh.len())
+    } else { None };
+    if opts.hash_items {
+        for it in &mut items { it.hash = Some(item_hash(it, &cw, &rh)); }
+    }
+    let (tc, tr) = if opts.responsive {
+        (minmax_tracks(&cw, &items.iter().map(|i| (i.col_start, i.col_end)).collect::<Vec<_>>()),
+         minmax_tracks(&rh, &items.iter().map(|i| (i.row_start, i.row_end)).collect::<Vec<_>>()))
+    } else if opts.use_fr { (fc.clone(), fr.clone()) } else { (tc, tr) };
+    GridLayout {
         template_columns:tc, template_rows:tr,
         fr_columns:fc, fr_rows:fr,
-        col_widths_px:cw, row_heights_px:rh, items,
-    }).map_err(|e| JsValue::from_str(&e.to_string()))
+        col_widths_px:cw, row_heights_px:rh, items, gap, overlaps, grid_template_areas, origin,
+        empty_cells, options: Some(opts.clone()), canvas_width,
+    }
+}
+
+/// `gap` back to per-axis px: ("row col" | "both") → (row, col).
+fn parse_gap(gap: &str) -> (Option<f64>, Option<f64>) {
+    let px: Vec<f64> = gap.split_whitespace().filter_map(|t| t.trim_end_matches("px").parse().ok()).collect();
+    let nz = |v: f64| (v > 0.0).then_some(v);
+    match px[..] {
+        [g] => (nz(g), nz(g)),
+        [r, c] => (nz(r), nz(c)),
+        _ => (None, None),
+    }
+}
+
+/// Re-insert collapsed gutter tracks of width `gap` between content tracks;
+/// the inverse of `drop_gutters` (start line `L → 2L - 1`, end `L → 2L - 2`).
+fn restore_gutters(tracks: &mut Vec<f64>, gap: f64, starts: &mut [&mut usize], ends: &mut [&mut usize]) {
+    let mut out = Vec::with_capacity(tracks.len() * 2);
+    for (i, &t) in tracks.iter().enumerate() { if i > 0 { out.push(gap); } out.push(t); }
+    *tracks = out;
+    for l in starts.iter_mut() { **l = **l * 2 - 1; }
+    for l in ends.iter_mut() { **l = (**l * 2).saturating_sub(2).max(1); }
+}
+
+/// 1-based line for canvas coordinate `v`: an existing line within `tol`,
+/// else a new one, splitting the track it falls in (or extending the grid
+/// past either end). Returns the line and, for a new line, its 1-based
+/// number — every item line at or after it must shift by one.
+fn line_for(tracks: &mut Vec<f64>, origin: &mut f64, v: f64, tol: f64) -> (usize, Option<usize>) {
+    let mut lines = Vec::with_capacity(tracks.len() + 1);
+    let mut at = *origin;
+    lines.push(at);
+    for t in tracks.iter() { at += t; lines.push(at); }
+    let near = find_idx(&lines, v);
+    if (lines[near] - v).abs() <= tol { return (near + 1, None); }
+    if v < lines[0] {
+        tracks.insert(0, (lines[0] - v).round().max(1.0));
+        *origin = v;
+        return (1, Some(1));
+    }
+    let last = lines.len() - 1;
+    if v > lines[last] {
+        tracks.push((v - lines[last]).round().max(1.0));
+        return (last + 2, Some(last + 2));
+    }
+    // lines[k] < v < lines[k + 1]
+    let k = lines.windows(2).position(|w| w[0] < v && v < w[1]).unwrap_or(near.min(last - 1));
+    let left = (v - lines[k]).round().max(1.0);
+    let right = (tracks[k] - left).max(1.0);
+    tracks.splice(k..=k, [left, right]);
+    (k + 2, Some(k + 2))
+}
+
+pub(crate) fn update_grid(mut layout: GridLayout, node: &GridInputNode) -> Result<GridLayout, String> {
+    if ![node.x, node.y, node.w, node.h].iter().all(|v| v.is_finite()) {
+        return Err(format!("[grid] non-finite geometry on node {}", node.id));
+    }
+    if layout.col_widths_px.is_empty() || layout.row_heights_px.is_empty() {
+        return Err("[grid] layout has no tracks".into());
+    }
+    // Layouts from before `options` was serialized: recover what the output shows.
+    let opts = layout.options.take().unwrap_or_else(|| {
+        let responsive = layout.template_columns.contains("minmax(");
+        GridOptions {
+            hash_items: layout.items.iter().any(|i| i.hash.is_some()),
+            use_fr: !responsive && layout.template_columns == layout.fr_columns,
+            responsive,
+            named_areas: layout.grid_template_areas.is_some(),
+            ..GridOptions::default()
+        }
+    });
+    if opts.snap_tol.is_nan() || opts.snap_tol < 0.0 { return Err(format!("[grid] snapTol must be >= 0, got {}", opts.snap_tol)); }
+    let (row_gap, col_gap) = layout.gap.as_deref().map(parse_gap).unwrap_or((None, None));
+    let (mut cw, mut rh) = (std::mem::take(&mut layout.col_widths_px), std::mem::take(&mut layout.row_heights_px));
+    let mut items = std::mem::take(&mut layout.items);
+    if let Some(g) = col_gap {
+        let (mut s, mut e): (Vec<&mut usize>, Vec<&mut usize>) = items.iter_mut().map(|i| (&mut i.col_start, &mut i.col_end)).unzip();
+        restore_gutters(&mut cw, g, &mut s, &mut e);
+    }
+    if let Some(g) = row_gap {
+        let (mut s, mut e): (Vec<&mut usize>, Vec<&mut usize>) = items.iter_mut().map(|i| (&mut i.row_start, &mut i.row_end)).unzip();
+        restore_gutters(&mut rh, g, &mut s, &mut e);
+    }
+    let (mut ox, mut oy) = layout.origin;
+    let idx = match items.iter().position(|i| i.id == node.id) {
+        Some(i) => i,
+        None => { items.push(GridItem { id: node.id.clone(), col_start: 1, col_end: 1, row_start: 1, row_end: 1, hash: None, z_index: None, area: None }); items.len() - 1 }
+    };
+    let tol = opts.snap_tol;
+    let mut place = |tracks: &mut Vec<f64>, origin: &mut f64, v: f64, lines: &mut dyn FnMut(&mut GridItem) -> [&mut usize; 2]| {
+        let (line, inserted) = line_for(tracks, origin, v, tol);
+        if let Some(at) = inserted {
+            for it in items.iter_mut() { for l in lines(it) { if *l >= at { *l += 1; } } }
+        }
+        line
+    };
+    let col_start = place(&mut cw, &mut ox, node.x, &mut |i| [&mut i.col_start, &mut i.col_end]);
+    let col_end   = place(&mut cw, &mut ox, node.x + node.w, &mut |i| [&mut i.col_start, &mut i.col_end]);
+    let row_start = place(&mut rh, &mut oy, node.y, &mut |i| [&mut i.row_start, &mut i.row_end]);
+    let row_end   = place(&mut rh, &mut oy, node.y + node.h, &mut |i| [&mut i.row_start, &mut i.row_end]);
+    let it = &mut items[idx];
+    (it.col_start, it.col_end, it.row_start, it.row_end) = (col_start, col_end, row_start, row_end);
+    // Keep gutters collapsed only when the layout had them collapsed.
+    Ok(finish_grid(cw, rh, items, (ox, oy), layout.canvas_width, &opts, layout.gap.is_some()))
+}
+
+#[wasm_bindgen]
+pub fn absolute_to_grid(nodes_json: String, canvas_width: f64) -> Result<String, JsValue> {
+    absolute_to_grid_with(nodes_json, canvas_width, String::new())
+}
+
+/// `absolute_to_grid` with a `GridOptions` JSON object — pass "" for defaults.
+#[wasm_bindgen]
+pub fn absolute_to_grid_with(nodes_json: String, canvas_width: f64, options_json: String) -> Result<String, JsValue> {
+    let nodes: Vec<GridInputNode> = serde_json::from_str(&nodes_json)
+        .map_err(|e| JsValue::from_str(&format!("[grid] parse: {}", e)))?;
+    let opts: GridOptions = if options_json.trim().is_empty() { GridOptions::default() } else {
+        serde_json::from_str(&options_json)
+            .map_err(|e| JsValue::from_str(&format!("[grid] parse options: {}", e)))?
+    };
+    let layout = grid_layout(&nodes, canvas_width, &opts).map_err(|e| JsValue::from_str(&e))?;
+    serde_json::to_string(&layout).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Re-place one moved node in a layout from `absolute_to_grid*` without
+/// recomputing the grid: its edges snap to the existing lines, and a track
+/// is split (or the grid extended) only where an edge crosses into one.
+/// Other items keep their tracks; tracks the node vacated stay. Collapsed
+/// gutters stay collapsed while they remain uniform. A node id not in the
+/// layout is added. The layout's `options` and `canvasWidth` are reused;
+/// layouts without them get defaults, with hashes, areas and `useFr` /
+/// `responsive` templates recovered from the output.
+#[wasm_bindgen]
+pub fn update_grid_item(layout_json: String, node_json: String) -> Result<String, JsValue> {
+    let layout: GridLayout = serde_json::from_str(&layout_json)
+        .map_err(|e| JsValue::from_str(&format!("[grid] parse layout: {}", e)))?;
+    let node: GridInputNode = serde_json::from_str(&node_json)
+        .map_err(|e| JsValue::from_str(&format!("[grid] parse: {}", e)))?;
+    let layout = update_grid(layout, &node).map_err(|e| JsValue::from_str(&e))?;
+    serde_json::to_string(&layout).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Same conversion as `absolute_to_grid`, rendered as a stylesheet: a
+/// `.vectra-grid` container rule (display, templates, gap) plus one
+/// `[data-vid="…"]` rule per item with its `grid-column` / `grid-row`.
+#[wasm_bindgen]
+pub fn absolute_to_grid_css(nodes_json: String, canvas_width: f64) -> Result<String, JsValue> {
+    let nodes: Vec<GridInputNode> = serde_json::from_str(&nodes_json)
+        .map_err(|e| JsValue::from_str(&format!("[grid] parse: {}", e)))?;
+    let layout = grid_layout(&nodes, canvas_width, &GridOptions::default()).map_err(|e| JsValue::from_str(&e))?;
+    Ok(grid_css(&layout))
+}
+
+pub(crate) fn grid_css(l: &GridLayout) -> String {
+    let mut css = format!(".vectra-grid {{\n  display: grid;\n  grid-template-columns: {};\n  grid-template-rows: {};\n",
+        l.template_columns, l.template_rows);
+    if let Some(g) = &l.gap { css.push_str(&format!("  gap: {};\n", g)); }
+    if let Some(a) = &l.grid_template_areas { css.push_str(&format!("  grid-template-areas: {};\n", a)); }
+    css.push_str("}\n");
+    for it in &l.items {
+        let id = it.id.replace('\\', "\\\\").replace('"', "\\\"");
+        match &it.area {
+            Some(a) => css.push_str(&format!("\n[data-vid=\"{}\"] {{\n  grid-area: {};\n", id, a)),
+            None => css.push_str(&format!("\n[data-vid=\"{}\"] {{\n  grid-column: {} / {};\n  grid-row: {} / {};\n",
+                id, it.col_start, it.col_end, it.row_start, it.row_end)),
+        }
+        if let Some(z) = it.z_index { css.push_str(&format!("  z-index: {};\n", z)); }
+        css.push_str("}\n");
+    }
+    css
+}
+
+#[derive(Serialize)]
+pub struct LayoutGuess { pub kind: &'static str, pub confidence: f64 }
+
+/// Guess the simplest layout model for a node set: `row`, `column` or `grid`,
+/// with a 0–1 confidence. Lets the auto-layout exporter pick flex vs grid.
+#[wasm_bindgen]
+pub fn detect_layout(nodes_json: String) -> Result<JsValue, JsValue> {
+    let nodes: Vec<GridInputNode> = serde_json::from_str(&nodes_json)
+        .map_err(|e| JsValue::from_str(&format!("[grid] parse: {}", e)))?;
+    let guess = layout_guess(&nodes).map_err(|e| JsValue::from_str(&e))?;
+    Ok(serde_wasm_bindgen::to_value(&guess)?)
+}
+
+/// Number of bands after merging overlapping `[start, end)` intervals.
+/// Intervals are shrunk by `SNAP_TOL` so edges that merely touch stay separate.
+fn band_count(mut iv: Vec<(f64, f64)>) -> usize {
+    iv.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let mut bands = 0; let mut end = f64::MIN;
+    for (s, e) in iv {
+        let (s, e) = if e - s > 2.0 * SNAP_TOL { (s + SNAP_TOL, e - SNAP_TOL) } else { (s, e) };
+        if s >= end { bands += 1; end = e; } else { end = end.max(e); }
+    }
+    bands
+}
+
+/// Rows = horizontal bands of overlapping y-extents, columns = vertical bands of
+/// x-extents. A single row with one column per node is a clear `row` (and vice
+/// versa); a fully occupied rows × cols matrix is a clear `grid`. Scores degrade
+/// as nodes stack inside a band or matrix cells stay empty.
+pub(crate) fn layout_guess(nodes: &[GridInputNode]) -> Result<LayoutGuess, String> {
+    if nodes.is_empty() { return Err("[grid] no nodes".into()); }
+    let n = nodes.len() as f64;
+    let rows = band_count(nodes.iter().map(|m| (m.y, m.y + m.h)).collect()) as f64;
+    let cols = band_count(nodes.iter().map(|m| (m.x, m.x + m.w)).collect()) as f64;
+    let row = cols / n / rows;
+    let column = rows / n / cols;
+    let grid = if rows > 1.0 && cols > 1.0 { (n / (rows * cols)).min(1.0) } else { 0.0 };
+    let (kind, confidence) = [("row", row), ("column", column), ("grid", grid)].into_iter()
+        .fold(("row", f64::MIN), |best, c| if c.1 > best.1 { c } else { best });
+    Ok(LayoutGuess { kind, confidence: (confidence * 1000.0).round() / 1000.0 })
+}
+
+#[derive(Serialize)] #[serde(rename_all="camelCase")]
+pub struct FlexItem { pub id: String, pub align_self: &'static str }
+
+#[derive(Serialize)] #[serde(rename_all="camelCase")]
+pub struct FlexLayout {
+    pub direction: &'static str,
+    /// Mean main-axis spacing between neighbours, rounded px.
+    pub gap: f64,
+    /// Items in main-axis order.
+    pub items: Vec<FlexItem>,
+}
+
+/// Sibling of `absolute_to_grid` for layouts that are really one row or one
+/// column. Errors with `[flex] not a single row or column` on true 2D
+/// arrangements so the caller can fall back to the grid converter.
+#[wasm_bindgen]
+pub fn absolute_to_flex(nodes_json: String) -> Result<String, JsValue> {
+    let nodes: Vec<GridInputNode> = serde_json::from_str(&nodes_json)
+        .map_err(|e| JsValue::from_str(&format!("[flex] parse: {}", e)))?;
+    let layout = flex_layout(&nodes).map_err(|e| JsValue::from_str(&e))?;
+    serde_json::to_string(&layout).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Cross-axis alignment of `[s, e)` inside the line `[lo, hi)`, matched
+/// within `SNAP_TOL`: full extent → stretch, then start, end, centre.
+fn align_in(s: f64, e: f64, lo: f64, hi: f64) -> &'static str {
+    let near = |a: f64, b: f64| (a - b).abs() <= SNAP_TOL;
+    match (near(s, lo), near(e, hi)) {
+        (true, true)  => "stretch",
+        (true, false) => "flex-start",
+        (false, true) => "flex-end",
+        _ if near((s + e) / 2.0, (lo + hi) / 2.0) => "center",
+        _ => "flex-start",
+    }
+}
+
+/// A row is one y-band whose nodes occupy distinct x-bands (and vice versa
+/// for a column); anything else is a 2D layout.
+pub(crate) fn flex_layout(nodes: &[GridInputNode]) -> Result<FlexLayout, String> {
+    if nodes.is_empty() { return Err("[flex] no nodes".into()); }
+    let rows = band_count(nodes.iter().map(|m| (m.y, m.y + m.h)).collect());
+    let cols = band_count(nodes.iter().map(|m| (m.x, m.x + m.w)).collect());
+    let row = rows == 1 && cols == nodes.len();
+    let column = cols == 1 && rows == nodes.len();
+    if !(row || column) {
+        return Err("[flex] not a single row or column".into());
+    }
+    // (main start, main end, cross start, cross end) per node
+    let span = |n: &GridInputNode| if row { (n.x, n.x + n.w, n.y, n.y + n.h) } else { (n.y, n.y + n.h, n.x, n.x + n.w) };
+    let mut order: Vec<&GridInputNode> = nodes.iter().collect();
+    order.sort_by(|a, b| span(a).0.partial_cmp(&span(b).0).unwrap_or(std::cmp::Ordering::Equal));
+    let lo = order.iter().map(|n| span(n).2).fold(f64::MAX, f64::min);
+    let hi = order.iter().map(|n| span(n).3).fold(f64::MIN, f64::max);
+    let gaps: Vec<f64> = order.windows(2).map(|w| (span(w[1]).0 - span(w[0]).1).max(0.0)).collect();
+    let gap = if gaps.is_empty() { 0.0 } else { (gaps.iter().sum::<f64>() / gaps.len() as f64).round() };
+    let items = order.iter().map(|n| {
+        let (_, _, s, e) = span(n);
+        FlexItem { id: n.id.clone(), align_self: align_in(s, e, lo, hi) }
+    }).collect();
+    Ok(FlexLayout { direction: if row { "row" } else { "column" }, gap, items })
+}
+
+/// Stateful companion to `absolute_to_grid` for editors that re-convert often:
+/// mutate the node set with `add` / `remove`, then call `build()`.
+/// `add` / `remove` only edit the list; all the O(N log N) breakpoint sorting
+/// and track placement happens in `build()`.
+#[wasm_bindgen]
+pub struct GridBuilder {
+    nodes:        Vec<GridInputNode>,
+    canvas_width: f64,
+    opts:         GridOptions,
+}
+
+#[wasm_bindgen]
+impl GridBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas_width: f64) -> GridBuilder {
+        GridBuilder { nodes: Vec::new(), canvas_width, opts: GridOptions::default() }
+    }
+
+    /// `GridOptions` JSON object — "" resets to defaults.
+    pub fn set_options(&mut self, options_json: String) -> Result<(), JsValue> {
+        self.opts = if options_json.trim().is_empty() { GridOptions::default() } else {
+            serde_json::from_str(&options_json)
+                .map_err(|e| JsValue::from_str(&format!("[grid] parse options: {}", e)))?
+        };
+        Ok(())
+    }
+
+    /// Add one `{ id, x, y, w, h }` node; an existing node with the same id is replaced in place.
+    pub fn add(&mut self, node_json: String) -> Result<(), JsValue> {
+        let n: GridInputNode = serde_json::from_str(&node_json)
+            .map_err(|e| JsValue::from_str(&format!("[grid] parse: {}", e)))?;
+        self.insert(n);
+        Ok(())
+    }
+
+    /// Returns false when no node had that id.
+    pub fn remove(&mut self, id: String) -> bool {
+        let before = self.nodes.len();
+        self.nodes.retain(|n| n.id != id);
+        self.nodes.len() != before
+    }
+
+    pub fn len(&self) -> usize { self.nodes.len() }
+    pub fn is_empty(&self) -> bool { self.nodes.is_empty() }
+    pub fn clear(&mut self) { self.nodes.clear(); }
+
+    /// Same output as `absolute_to_grid_with` over the current node set.
+    pub fn build(&self) -> Result<String, JsValue> {
+        let layout = grid_layout(&self.nodes, self.canvas_width, &self.opts).map_err(|e| JsValue::from_str(&e))?;
+        serde_json::to_string(&layout).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+impl GridBuilder {
+    pub(crate) fn insert(&mut self, n: GridInputNode) {
+        match self.nodes.iter_mut().find(|m| m.id == n.id) {
+            Some(slot) => *slot = n,
+            None => self.nodes.push(n),
+        }
+    }
+}
+
+// ── §18 SceneQueries ──────────────────────────────────────────────────────────
+
+#[derive(Serialize)]
+pub struct StackingConflict { pub a: String, pub b: String, pub z_index: Option<i64> }
+
+/// Canvas rect from `props.style` left/top/width/height. None when the node
+/// has no explicit size — its painted extent is unknown.
+pub(crate) fn node_rect(n: &VectraNode) -> Option<SimpleRect> {
+    let style = n.other.get("props").and_then(|p| p.get("style"));
+    let get = |k: &str| style.and_then(|s| s.get(k));
+    let (w, h) = (px_val(get("width")), px_val(get("height")));
+    if w <= 0.0 || h <= 0.0 { return None; }
+    Some(SimpleRect::new(px_val(get("left")), px_val(get("top")), w, h))
+}
+
+pub(crate) fn is_hidden(n: &VectraNode) -> bool {
+    n.other.get("hidden").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// `style.zIndex` as an integer; "auto"/absent → None.
+fn node_z(n: &VectraNode) -> Option<i64> {
+    let z = n.other.get("props")?.get("style")?.get("zIndex")?;
+    z.as_i64().or_else(|| z.as_f64().map(|f| f as i64))
+        .or_else(|| z.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+/// Find visibly overlapping siblings that share a z-index (or both have none),
+/// so paint order silently falls back to document order.
+/// Returns `[{ a, b, z_index }]`. Siblings only — each parent is its own
+/// stacking scope, and non-overlapping same-z pairs are not reported.
+#[wasm_bindgen]
+pub fn detect_stacking_conflicts(project_val: JsValue, root_id: String) -> Result<JsValue, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(serde_wasm_bindgen::to_value(&stacking_conflicts(&project, &root_id))?)
+}
+
+pub(crate) fn stacking_conflicts(project: &HashMap<String, VectraNode>, root_id: &str) -> Vec<StackingConflict> {
+    let mut out = Vec::new();
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut stack = vec![root_id];
+    while let Some(id) = stack.pop() {
+        if !visited.insert(id) { continue; }
+        let Some(n) = project.get(id) else { continue };
+        let Some(children) = &n.children else { continue };
+        let sibs: Vec<(&str, SimpleRect, Option<i64>)> = children.iter()
+            .filter_map(|cid| project.get(cid).map(|c| (cid, c)))
+            .filter(|(_, c)| !is_hidden(c))
+            .filter_map(|(cid, c)| node_rect(c).map(|r| (cid.as_str(), r, node_z(c))))
+            .collect();
+        for (i, (ia, ra, za)) in sibs.iter().enumerate() {
+            for (ib, rb, zb) in &sibs[i+1..] {
+                if za == zb && ra.overlaps(rb) {
+                    out.push(StackingConflict { a: ia.to_string(), b: ib.to_string(), z_index: *za });
+                }
+            }
+        }
+        for c in children { stack.push(c); }
+    }
+    out
+}
+
+/// `parent_id`'s children in paint order (back to front): ascending `zIndex`,
+/// with `auto`/absent counting as 0 like CSS, and document order breaking
+/// ties. Unknown parent → empty list. Shared by canvas render and export.
+#[wasm_bindgen]
+pub fn resolve_z_order(project_val: JsValue, parent_id: String) -> Result<JsValue, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(serde_wasm_bindgen::to_value(&z_order(&project, &parent_id))?)
+}
+
+pub(crate) fn z_order(project: &HashMap<String, VectraNode>, parent_id: &str) -> Vec<String> {
+    let Some(children) = project.get(parent_id).and_then(|p| p.children.as_ref()) else { return Vec::new() };
+    let mut ids: Vec<(i64, &String)> = children.iter()
+        .map(|c| (project.get(c).and_then(node_z).unwrap_or(0), c))
+        .collect();
+    ids.sort_by_key(|&(z, _)| z); // stable: ties keep document order
+    ids.into_iter().map(|(_, c)| c.clone()).collect()
+}
+
+/// Union of `root_id` and its visible descendants ("zoom to fit selection").
+/// Rects come from `node_rect`; children are positioned relative to their
+/// parent's `left`/`top`, so offsets accumulate down the tree. Hidden nodes
+/// drop out with their whole subtree, as in the exporters. `undefined` when
+/// nothing in the subtree has a size.
+#[wasm_bindgen]
+pub fn compute_bounds(project_val: JsValue, root_id: String) -> Result<JsValue, JsValue> {
+    let project: HashMap<String, VectraNode> = serde_wasm_bindgen::from_value(project_val)?;
+    Ok(serde_wasm_bindgen::to_value(&subtree_bounds(&project, &root_id))?)
+}
+
+pub(crate) fn subtree_bounds(project: &HashMap<String, VectraNode>, root_id: &str) -> Option<SimpleRect> {
+    let mut acc: Option<(f64, f64, f64, f64)> = None;
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut stack = vec![(root_id, 0.0, 0.0)];
+    while let Some((id, ox, oy)) = stack.pop() {
+        if !visited.insert(id) { continue; }
+        let Some(n) = project.get(id) else { continue };
+        if is_hidden(n) { continue; }
+        let style = n.other.get("props").and_then(|p| p.get("style"));
+        let (x, y) = (ox + px_val(style.and_then(|s| s.get("left"))), oy + px_val(style.and_then(|s| s.get("top"))));
+        if let Some(r) = node_rect(n) {
+            let (x1, y1) = (x + r.w, y + r.h);
+            acc = Some(match acc {
+                None => (x, y, x1, y1),
+                Some((a, b, c, d)) => (a.min(x), b.min(y), c.max(x1), d.max(y1)),
+            });
+        }
+        for c in n.children.iter().flatten() { stack.push((c, x, y)); }
+    }
+    acc.map(|(x0, y0, x1, y1)| SimpleRect::new(x0, y0, x1 - x0, y1 - y0))
+}
+
+// ── §20 TextMetrics ───────────────────────────────────────────────────────────
+
+/// `line-height: normal` approximation used until the DOM measures the node.
+const TEXT_LINE_HEIGHT: f64 = 1.2;
+
+#[derive(Serialize)]
+pub struct TextMetrics { pub lines: Vec<String>, pub width: f64, pub height: f64 }
+
+/// Estimated text box for initial auto-layout sizing: every character
+/// advances `avg_char_width`, lines are `font_size × 1.2` tall. With
+/// `max_width` words wrap greedily (runs of spaces collapse; a word longer
+/// than the line is broken); without it only explicit `\n` breaks lines.
+/// Returns `{ lines, width, height }` — `width` is the widest line.
+#[wasm_bindgen]
+pub fn measure_text(text: &str, font_size: f64, avg_char_width: f64, max_width: Option<f64>) -> Result<JsValue, JsValue> {
+    let m = text_metrics(text, font_size, avg_char_width, max_width).map_err(|e| JsValue::from_str(&e))?;
+    Ok(serde_wasm_bindgen::to_value(&m)?)
+}
+
+pub(crate) fn text_metrics(text: &str, font_size: f64, avg_char_width: f64, max_width: Option<f64>) -> Result<TextMetrics, String> {
+    if !(font_size.is_finite() && font_size > 0.0) { return Err(format!("[text] invalid font size {}", font_size)); }
+    if !(avg_char_width.is_finite() && avg_char_width > 0.0) { return Err(format!("[text] invalid char width {}", avg_char_width)); }
+    if let Some(mw) = max_width.filter(|mw| !(mw.is_finite() && *mw > 0.0)) {
+        return Err(format!("[text] invalid max width {}", mw));
+    }
+    // Characters per line; at least one so a narrow box still makes progress.
+    let cap = max_width.map(|mw| ((mw / avg_char_width).floor() as usize).max(1));
+
+    let mut lines: Vec<String> = Vec::new();
+    for para in text.split('\n').map(|p| p.strip_suffix('\r').unwrap_or(p)) {
+        let Some(cap) = cap else { lines.push(para.to_string()); continue };
+        let mut line = String::new();
+        let mut len = 0;
+        for word in para.split_whitespace() {
+            let mut chars: Vec<char> = word.chars().collect();
+            if len > 0 && len + 1 + chars.len() <= cap {
+                line.push(' '); line.extend(&chars); len += 1 + chars.len();
+                continue;
+            }
+            if len > 0 { lines.push(std::mem::take(&mut line)); }
+            while chars.len() > cap {
+                lines.push(chars.drain(..cap).collect());
+            }
+            len = chars.len();
+            line.extend(chars);
+        }
+        lines.push(line);
+    }
+
+    let widest = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    Ok(TextMetrics {
+        width: widest as f64 * avg_char_width,
+        height: lines.len() as f64 * font_size * TEXT_LINE_HEIGHT,
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Engine over `rects` with a pinned cell size, so bucketing is predictable.
+    fn engine(rects: &[(f64, f64, f64, f64)], cell: f64) -> LayoutEngine {
+        let mut e = LayoutEngine::new();
+        e.pin_cell_size(cell);
+        e.load_rects(rects.iter().map(|&(x, y, w, h)| SimpleRect::new(x, y, w, h)).collect());
+        e
+    }
+
+    #[test]
+    fn cell_for_point_reports_bucket() {
+        let e = engine(&[(10.0, 10.0, 50.0, 50.0), (150.0, 20.0, 200.0, 40.0), (-90.0, -90.0, 20.0, 20.0)], 100.0);
+        let c = e.cell_info(30.0, 30.0);
+        assert_eq!((c.gx, c.gy, c.indices), (0, 0, vec![0]));
+        // Rect 1 spans cells 1..=3 on x.
+        assert_eq!(e.cell_info(250.0, 50.0).indices, vec![1]);
+        assert_eq!(e.cell_info(320.0, 50.0).indices, vec![1]);
+        let neg = e.cell_info(-80.0, -80.0);
+        assert_eq!((neg.gx, neg.gy, neg.indices), (-1, -1, vec![2]));
+        assert!(e.cell_info(50.0, 500.0).indices.is_empty());
+    }
+
+    fn node(id: &str, x: f64, y: f64, w: f64, h: f64) -> GridInputNode {
+        GridInputNode { id: id.into(), x, y, w, h }
+    }
+
+    #[test]
+    fn fr_ratios_reduce_by_gcd() {
+        assert_eq!(fr_ratios(&[120.0, 240.0, 120.0]), Some(vec![1, 2, 1]));
+        assert_eq!(fr_join(&fr_ratios(&[120.0, 240.0, 120.0]).unwrap()), "1fr 2fr 1fr");
+        // GCD 1 but small: kept as is.
+        assert_eq!(fr_ratios(&[3.0, 5.0, 7.0]), Some(vec![3, 5, 7]));
+        // Coprime and large, or very different sizes: decimal fallback.
+        assert_eq!(fr_ratios(&[317.0, 523.0]), None);
+        assert_eq!(fr_ratios(&[10.0, 1000.0]), None);
+
+        let l = grid_layout(&[node("a", 0.0, 0.0, 120.0, 50.0), node("b", 120.0, 0.0, 240.0, 50.0), node("c", 360.0, 0.0, 120.0, 50.0)],
+                            0.0, &GridOptions::default()).unwrap();
+        assert_eq!(l.fr_columns, "1fr 2fr 1fr");
+    }
+
+    #[test]
+    fn engine_state_round_trips() {
+        let mut e = engine(&[(0.0, 0.0, 100.0, 100.0), (300.0, 40.0, 80.0, 60.0)], 120.0);
+        e.set_canvas_bounds(0.0, 0.0, 1440.0, 900.0);
+        e.load_guides(vec![("vertical".into(), 250.0), ("horizontal".into(), 500.0)]);
+        e.set_snap_thresholds(6.0, 3.0);
+        e.set_pixel_grid(4.0);
+        let blob = serde_json::to_string(&e.state()).unwrap();
+        // The spatial hash is rebuilt on import, never stored.
+        assert!(!blob.contains("grid\"") && blob.contains("\"pixelGrid\":4.0"), "{}", blob);
+
+        let mut back = LayoutEngine::new();
+        back.restore(serde_json::from_str(&blob).unwrap()).unwrap();
+        assert_eq!(serde_json::to_string(&back.state()).unwrap(), blob);
+        assert_eq!(back.cell_info(310.0, 50.0).indices, vec![1]);
+        let (a, b) = (e.snap(296.0, 250.0, 40.0, 40.0, 8.0, 8.0).result, back.snap(296.0, 250.0, 40.0, 40.0, 8.0, 8.0).result);
+        assert_eq!((a.x, a.y, a.guides.len()), (b.x, b.y, b.guides.len()));
+
+        let newer = blob.replace("\"version\":1", "\"version\":99");
+        assert!(back.restore(serde_json::from_str(&newer).unwrap()).is_err());
+    }
+
+    #[test]
+    fn decimal_precision_trims_zeros() {
+        assert_eq!(fmt_decimal(1.0 / 3.0, 3), "0.333");
+        assert_eq!(fmt_decimal(1.0, 3), "1");
+        assert_eq!(fmt_decimal(0.5, 3), "0.5");
+        assert_eq!(fmt_decimal(2.0 / 3.0, 1), "0.7");
+        assert_eq!(fmt_decimal(-0.0001, 2), "0");
+
+        // Coprime, large tracks fall back to decimal fr at the chosen precision.
+        let nodes = [node("a", 0.0, 0.0, 317.0, 10.0), node("b", 317.0, 0.0, 523.0, 10.0)];
+        let l = grid_layout(&nodes, 0.0, &GridOptions::default()).unwrap();
+        assert_eq!(l.fr_columns, "0.377fr 0.623fr");
+        let l = grid_layout(&nodes, 0.0, &GridOptions { precision: 1, ..GridOptions::default() }).unwrap();
+        assert_eq!(l.fr_columns, "0.4fr 0.6fr");
+    }
+
+    #[test]
+    fn detect_layout_row_column_grid() {
+        let row = [node("a", 0.0, 0.0, 100.0, 50.0), node("b", 120.0, 0.0, 100.0, 50.0), node("c", 240.0, 5.0, 100.0, 40.0)];
+        let g = layout_guess(&row).unwrap();
+        assert_eq!((g.kind, g.confidence), ("row", 1.0));
+
+        let col = [node("a", 0.0, 0.0, 200.0, 40.0), node("b", 0.0, 60.0, 200.0, 40.0), node("c", 10.0, 120.0, 180.0, 40.0)];
+        let g = layout_guess(&col).unwrap();
+        assert_eq!((g.kind, g.confidence), ("column", 1.0));
+
+        let grid = [node("a", 0.0, 0.0, 100.0, 100.0), node("b", 120.0, 0.0, 100.0, 100.0),
+                    node("c", 0.0, 120.0, 100.0, 100.0), node("d", 120.0, 120.0, 100.0, 100.0)];
+        let g = layout_guess(&grid).unwrap();
+        assert_eq!((g.kind, g.confidence), ("grid", 1.0));
+
+        assert!(layout_guess(&[]).is_err());
+    }
+
+    fn guide(orientation: &str, pos: f64, start: f64, end: f64, guide_type: &str) -> Guide {
+        Guide { orientation: orientation.into(), pos, start, end, guide_type: guide_type.into(), gap_px: 0.0, distance: None }
+    }
+
+    #[test]
+    fn collinear_guides_merge() {
+        let merged = merge_guides(vec![
+            guide("vertical", 100.0, 0.0, 50.0, "align"),
+            guide("horizontal", 100.0, 0.0, 50.0, "align"),
+            guide("vertical", 100.3, 200.0, 260.0, "align"),
+            guide("vertical", 140.0, 0.0, 50.0, "align"),
+        ], GUIDE_MERGE_TOL);
+        let spans: Vec<_> = merged.iter().map(|g| (g.orientation.as_str(), g.pos, g.start, g.end)).collect();
+        assert_eq!(spans, [("vertical", 100.0, 0.0, 260.0), ("horizontal", 100.0, 0.0, 50.0), ("vertical", 140.0, 0.0, 50.0)]);
+        // Gap ticks are measurements, never merged.
+        assert_eq!(merge_guides(vec![guide("vertical", 5.0, 0.0, 1.0, "gap"), guide("vertical", 5.0, 2.0, 3.0, "gap")], GUIDE_MERGE_TOL).len(), 2);
+    }
+
+    #[test]
+    fn manual_guides_win_over_siblings() {
+        let mut e = engine(&[(100.0, 300.0, 100.0, 100.0)], 100.0);
+        e.load_guides(vec![("vertical".into(), 203.0), ("horizontal".into(), 52.0), ("diagonal".into(), 1.0)]);
+        assert_eq!(e.manual_guides().len(), 2);
+        // Left edge 198: 2px from the sibling's right edge, 5px from the guide.
+        let r = e.snap(198.0, 300.0, 40.0, 40.0, 8.0, 8.0).result;
+        assert_eq!(r.x, 203.0);
+        let g = &r.guides[0];
+        assert_eq!((g.orientation.as_str(), g.pos, g.guide_type.as_str()), ("vertical", 203.0, "manual"));
+        // Center (y + 20) snaps to the horizontal guide.
+        assert_eq!(e.snap(500.0, 30.0, 40.0, 40.0, 8.0, 8.0).result.y, 32.0);
+        e.load_guides(Vec::new());
+        assert_eq!(e.snap(198.0, 300.0, 40.0, 40.0, 8.0, 8.0).result.x, 200.0);
+    }
+
+    #[test]
+    fn grid_with_negative_coordinates() {
+        let l = grid_layout(&[node("a", -200.0, 0.0, 100.0, 50.0), node("b", -50.0, 0.0, 100.0, 50.0),
+                              node("c", -200.0, 60.0, 250.0, 40.0)], 0.0, &GridOptions::default()).unwrap();
+        // Lines at -200 / -100 / -50 / 50: tracks stay positive and the origin keeps the offset.
+        assert_eq!(l.col_widths_px, [100.0, 50.0, 100.0]);
+        assert!(l.row_heights_px.iter().all(|&h| h > 0.0));
+        assert_eq!(l.origin, (-200.0, 0.0));
+        let cols: Vec<_> = l.items.iter().map(|i| (i.id.as_str(), i.col_start, i.col_end)).collect();
+        assert_eq!(cols, [("a", 1, 2), ("b", 3, 4), ("c", 1, 4)]);
+
+        // Without the spanning row the 50px gutter collapses into `gap`.
+        let l = grid_layout(&[node("a", -200.0, 0.0, 100.0, 50.0), node("b", -50.0, 0.0, 100.0, 50.0)],
+                            0.0, &GridOptions::default()).unwrap();
+        assert_eq!((l.template_columns.as_str(), l.gap.as_deref()), ("100px 100px", Some("0px 50px")));
+        assert_eq!((l.items[1].col_start, l.items[1].col_end), (2, 3));
+    }
+
+    #[test]
+    fn snap_excluding_ignores_listed_rects() {
+        let e = engine(&[(100.0, 0.0, 50.0, 50.0), (400.0, 300.0, 50.0, 50.0)], 100.0);
+        let hit = e.snap(97.0, 70.0, 50.0, 50.0, 8.0, 8.0);
+        assert!(hit.snapped_x && !hit.snapped_y);
+        assert_eq!(hit.result.x, 100.0);
+        // The dragged rect itself (index 0) must not attract its own edge.
+        let hit = e.snap_excluding(97.0, 70.0, 50.0, 50.0, 8.0, 8.0, &[0]);
+        assert!(!hit.snapped_x && hit.result.guides.is_empty());
+        assert_eq!(hit.result.x, 97.0);
+    }
+
+    #[test]
+    fn update_grid_reuses_layout_options() {
+        let opts = GridOptions { precision: 1, snap_tol: 10.0, ..GridOptions::default() };
+        let l = grid_layout(&[node("a", 0.0, 0.0, 317.0, 50.0), node("b", 317.0, 0.0, 523.0, 50.0)], 1000.0, &opts).unwrap();
+        assert_eq!(l.fr_columns, "0.3fr 0.5fr");
+        let blob = serde_json::to_string(&l).unwrap();
+        assert!(blob.contains("\"snapTol\":10.0") && blob.contains("\"canvasWidth\":1000.0"), "{}", blob);
+
+        // 8px off the shared line: inside snapTol 10, so no track is split.
+        let l = update_grid(serde_json::from_str(&blob).unwrap(), &node("b", 325.0, 0.0, 515.0, 50.0)).unwrap();
+        assert_eq!(l.col_widths_px, [317.0, 523.0]);
+        assert_eq!((l.fr_columns.as_str(), l.canvas_width), ("0.3fr 0.5fr", 1000.0));
+        assert_eq!(l.options.as_ref().map(|o| (o.precision, o.snap_tol)), Some((1, 10.0)));
+
+        let bad = GridLayout { options: Some(GridOptions { snap_tol: -1.0, ..opts }), ..l };
+        assert!(update_grid(bad, &node("b", 0.0, 0.0, 10.0, 10.0)).err().is_some_and(|e| e.contains("snapTol")));
+    }
 }
@@ -6,6 +6,7 @@
 // ║                                                                              ║
 // ║  Module map:                                                                 ║
 // ║    layout.rs    §1  LayoutEngine   §6  absolute_to_grid                     ║
+// ║                 §18 SceneQueries   §20 TextMetrics                          ║
 // ║    state.rs     §2  HistoryManager §8  TreeManager  §17 structural_key      ║
 // ║    compiler.rs  §3  SwcCompiler    §13 CodeSanitizer §14 ComponentAnalyzer  ║
 // ║                 §15 CodeWrapper                                              ║
@@ -15,6 +16,7 @@
 // ║    ai.rs        §9  JsonRepair     §10 AIMerger                             ║
 // ║    thumbnail.rs §12 ThumbnailEngine                                         ║
 // ║    figma.rs     §16 FigmaConverter                                          ║
+// ║    media.rs     §19 ImageProbe                                              ║
 // ╚══════════════════════════════════════════════════════════════════════════════╝
 
 pub mod layout;
@@ -25,6 +27,7 @@ pub mod codegen;
 pub mod ai;
 pub mod thumbnail;
 pub mod figma;
+pub mod media;
 
 use wasm_bindgen::prelude::*;
 
@@ -0,0 +1,105 @@
+// ══════════════════════════════════════════════════════════════════════════════
+// media.rs  —  §19 ImageProbe
+// ══════════════════════════════════════════════════════════════════════════════
+//
+//  image_dimensions — natural width/height of a PNG, JPEG, GIF or WebP from
+//  its header bytes alone (no pixel decode). Sizes a new image node on
+//  import without going through an off-screen <img> on the main thread.
+
+use wasm_bindgen::prelude::*;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ImageDimensions { pub width: u32, pub height: u32, pub format: String }
+
+/// `{ width, height, format }` with `format` one of `png`, `jpeg`, `gif`,
+/// `webp`. Only the header is read; unknown or truncated data is an error.
+#[wasm_bindgen]
+pub fn image_dimensions(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    let dims = probe(bytes).map_err(|e| JsValue::from_str(&e))?;
+    Ok(serde_wasm_bindgen::to_value(&dims)?)
+}
+
+pub(crate) fn probe(b: &[u8]) -> Result<ImageDimensions, String> {
+    let (format, dims) = if b.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("png", png(b))
+    } else if b.starts_with(&[0xFF, 0xD8]) {
+        ("jpeg", jpeg(b))
+    } else if b.starts_with(b"GIF87a") || b.starts_with(b"GIF89a") {
+        ("gif", b.get(6..10).map(|h| (le16(&h[0..2]), le16(&h[2..4]))))
+    } else if b.len() >= 12 && &b[0..4] == b"RIFF" && &b[8..12] == b"WEBP" {
+        ("webp", webp(b))
+    } else {
+        return Err("[image] unsupported format (expected PNG, JPEG, GIF or WebP)".into());
+    };
+    match dims {
+        Some((width, height)) if width > 0 && height > 0 => Ok(ImageDimensions { width, height, format: format.into() }),
+        Some((w, h)) => Err(format!("[image] {} header has zero size {}x{}", format, w, h)),
+        None => Err(format!("[image] truncated or corrupt {} header", format)),
+    }
+}
+
+fn be16(b: &[u8]) -> u32 { u16::from_be_bytes([b[0], b[1]]) as u32 }
+fn le16(b: &[u8]) -> u32 { u16::from_le_bytes([b[0], b[1]]) as u32 }
+fn le24(b: &[u8]) -> u32 { b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 }
+
+/// IHDR must be the first chunk: width / height are big-endian at 16 / 20.
+fn png(b: &[u8]) -> Option<(u32, u32)> {
+    let h = b.get(12..24)?;
+    if &h[0..4] != b"IHDR" { return None; }
+    Some((u32::from_be_bytes(h[4..8].try_into().ok()?), u32::from_be_bytes(h[8..12].try_into().ok()?)))
+}
+
+/// Walk marker segments up to the first SOFn frame header.
+fn jpeg(b: &[u8]) -> Option<(u32, u32)> {
+    let mut i = 2;
+    loop {
+        if *b.get(i)? != 0xFF { return None; }
+        while *b.get(i)? == 0xFF { i += 1; }
+        let m = b[i];
+        i += 1;
+        match m {
+            // Standalone markers carry no length.
+            0x01 | 0xD0..=0xD7 => continue,
+            // End of image / start of scan before any frame header.
+            0xD9 | 0xDA => return None,
+            // SOF0–SOF15 minus DHT (C4), JPG (C8), DAC (CC): [len:2][precision:1][h:2][w:2].
+            0xC0..=0xCF if m != 0xC4 && m != 0xC8 && m != 0xCC => {
+                let f = b.get(i..i + 7)?;
+                return Some((be16(&f[5..7]), be16(&f[3..5])));
+            }
+            _ => {
+                let len = be16(b.get(i..i + 2)?) as usize;
+                if len < 2 { return None; }
+                i += len;
+            }
+        }
+    }
+}
+
+/// First chunk after the RIFF header decides the bitstream flavour.
+fn webp(b: &[u8]) -> Option<(u32, u32)> {
+    let chunk = b.get(12..16)?;
+    let d = b.get(20..)?;
+    match chunk {
+        // Lossy: 3-byte frame tag, start code 9D 01 2A, then 14-bit w / h.
+        b"VP8 " => {
+            let d = d.get(..10)?;
+            if d[3..6] != [0x9D, 0x01, 0x2A] { return None; }
+            Some((le16(&d[6..8]) & 0x3FFF, le16(&d[8..10]) & 0x3FFF))
+        }
+        // Lossless: signature 0x2F, then 14-bit (w - 1) and (h - 1) packed LE.
+        b"VP8L" => {
+            let d = d.get(..5)?;
+            if d[0] != 0x2F { return None; }
+            let bits = u32::from_le_bytes(d[1..5].try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        // Extended: 4 flag bytes, then 24-bit (w - 1) and (h - 1).
+        b"VP8X" => {
+            let d = d.get(..10)?;
+            Some((le24(&d[4..7]) + 1, le24(&d[7..10]) + 1))
+        }
+        _ => None,
+    }
+}
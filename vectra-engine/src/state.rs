@@ -3,8 +3,15 @@
 // ══════════════════════════════════════════════════════════════════════════════
 //
 //  §2  HistoryManager — LZ4-compressed undo/redo (VecDeque, O(1) ops)
+//      Optional branching mode keeps abandoned redo futures as branches.
 //      FNV-1a dedup skips consecutive identical states before compression.
-//      LZ4 is 5-10× faster than gzip; old gzip frames are auto-decoded.
+//      Snapshots may carry a label that travels with them through eviction.
+//      LZ4 is 5-10× faster than gzip; new_with_compression opts into gzip
+//      at a chosen level (0 = raw). Frames self-describe their codec.
+//      new_with_diff stores splice deltas between keyframes (every 10th).
+//      serialize / deserialize round-trip the whole session as a binary blob.
+//      undo_diff / redo_diff return an RFC 6902 patch instead of the full state.
+//      mark_saved / is_dirty track the saved snapshot across undo / redo.
 //
 //  §8  TreeManager — delete_subtree, clone_subtree, find_parent, build_parent_map
 //      All take the full project as a JSON string, return JSON.
@@ -16,11 +23,11 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::prelude::*;
 use wasm_bindgen::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use serde_json::{json, Value};
-use flate2::read::GzDecoder;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 
-// ── Shared utility — used by state, ai, codegen, figma ───────────────────────
+// ── Shared utility — used by state, ai, codegen, figma, layout ──────────────
 
 /// Generate 32 lowercase hex chars using getrandom (RFC 4122 style).
 pub(crate) fn uuid_hex() -> String {
@@ -29,14 +36,8 @@ pub(crate) fn uuid_hex() -> String {
     buf.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
-// ── §2 HistoryManager — compression internals ────────────────────────────────
-
-const CODEC_LZ4:  u8 = 0x4C;
-const CODEC_GZIP: u8 = 0x47;
-
-struct Frame { data: Vec<u8>, hash: u64 }
-
-fn fnv1a(s: &str) -> u64 {
+/// FNV-1a 64-bit — fast, non-cryptographic content hash.
+pub(crate) fn fnv1a(s: &str) -> u64 {
     const O: u64 = 14695981039346656037;
     const P: u64 = 1099511628211;
     let mut h = O;
@@ -44,6 +45,18 @@ fn fnv1a(s: &str) -> u64 {
     h
 }
 
+// ── §2 HistoryManager — compression internals ────────────────────────────────
+
+const CODEC_LZ4:  u8 = 0x4C;
+const CODEC_GZIP: u8 = 0x47;
+const CODEC_RAW:  u8 = 0x52;
+/// Splice against the previous frame: `[D][prefix u32][suffix u32][lz4 middle]`.
+const CODEC_DELTA: u8 = 0x44;
+/// Diff mode keeps every Nth snapshot whole, bounding reconstruction to N-1 splices.
+const KEYFRAME_EVERY: usize = 10;
+
+struct Frame { data: Vec<u8>, hash: u64, label: String }
+
 fn compress_lz4(d: &str) -> Vec<u8> {
     let b = d.as_bytes(); let ol = b.len() as u32;
     let p = lz4_flex::compress(b);
@@ -61,6 +74,7 @@ fn decompress_frame(f: &Frame) -> Option<String> {
     if f.data.is_empty() { return None; }
     match f.data[0] {
         CODEC_LZ4  => decompress_lz4(&f.data[1..]),
+        CODEC_RAW  => String::from_utf8(f.data[1..].to_vec()).ok(),
         CODEC_GZIP => {
             let mut d = GzDecoder::new(&f.data[1..]);
             let mut s = String::new();
@@ -71,7 +85,92 @@ fn decompress_frame(f: &Frame) -> Option<String> {
     }
 }
 
-fn make_frame(s: &str) -> Frame { Frame { data: compress_lz4(s), hash: fnv1a(s) } }
+/// `None` → LZ4 (default); `Some(0)` → stored raw; `Some(1..=9)` → gzip at that level.
+fn compress_state(s: &str, gzip: Option<u32>) -> Vec<u8> {
+    match gzip {
+        None    => compress_lz4(s),
+        Some(0) => { let mut o = vec![CODEC_RAW]; o.extend_from_slice(s.as_bytes()); o }
+        Some(l) => {
+            let mut e = GzEncoder::new(vec![CODEC_GZIP], Compression::new(l));
+            e.write_all(s.as_bytes()).ok();
+            e.finish().unwrap_or_default()
+        }
+    }
+}
+
+fn make_frame(s: &str, label: String, gzip: Option<u32>) -> Frame {
+    Frame { data: compress_state(s, gzip), hash: fnv1a(s), label }
+}
+
+fn is_delta(f: &Frame) -> bool { f.data.first() == Some(&CODEC_DELTA) }
+
+/// Encode `s` as the span that differs from `base` (common prefix/suffix
+/// kept by length). A one-node edit in a large project costs a few bytes.
+fn make_delta(base: &str, s: &str, label: String) -> Frame {
+    let (a, b) = (base.as_bytes(), s.as_bytes());
+    let pre = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let max_suf = a.len().min(b.len()) - pre;
+    let suf = a.iter().rev().zip(b.iter().rev()).take(max_suf).take_while(|(x, y)| x == y).count();
+    let mid = lz4_flex::compress_prepend_size(&b[pre..b.len() - suf]);
+    let mut o = Vec::with_capacity(1 + 8 + mid.len());
+    o.push(CODEC_DELTA);
+    o.extend_from_slice(&(pre as u32).to_le_bytes());
+    o.extend_from_slice(&(suf as u32).to_le_bytes());
+    o.extend_from_slice(&mid);
+    Frame { data: o, hash: fnv1a(s), label }
+}
+
+fn apply_delta(base: &str, f: &Frame) -> Option<String> {
+    let d = f.data.get(1..)?;
+    if d.len() < 8 { return None; }
+    let pre = u32::from_le_bytes(d[..4].try_into().ok()?) as usize;
+    let suf = u32::from_le_bytes(d[4..8].try_into().ok()?) as usize;
+    let b = base.as_bytes();
+    if pre + suf > b.len() { return None; }
+    let mid = lz4_flex::decompress_size_prepended(&d[8..]).ok()?;
+    let mut o = Vec::with_capacity(pre + mid.len() + suf);
+    o.extend_from_slice(&b[..pre]); o.extend_from_slice(&mid); o.extend_from_slice(&b[b.len() - suf..]);
+    String::from_utf8(o).ok()
+}
+
+// ── §2 HistoryManager — JSON Patch diffs ─────────────────────────────────────
+
+/// RFC 6901 pointer segment: `~` → `~0`, `/` → `~1`.
+fn pointer_seg(k: &str) -> String { k.replace('~', "~0").replace('/', "~1") }
+
+/// RFC 6902 patch turning `from` into `to`. Objects diff per key; arrays of
+/// equal length diff per index, otherwise are replaced whole. A state that
+/// is not valid JSON yields a single root `replace` carrying the raw string.
+pub(crate) fn json_patch(from: &str, to: &str) -> String {
+    let mut ops: Vec<Value> = Vec::new();
+    match (serde_json::from_str::<Value>(from), serde_json::from_str::<Value>(to)) {
+        (Ok(a), Ok(b)) => diff_values(&a, &b, "", &mut ops),
+        _ => if from != to { ops.push(json!({ "op": "replace", "path": "", "value": to })); },
+    }
+    Value::Array(ops).to_string()
+}
+
+fn diff_values(a: &Value, b: &Value, path: &str, ops: &mut Vec<Value>) {
+    if a == b { return; }
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => {
+            for k in x.keys().filter(|k| !y.contains_key(*k)) {
+                ops.push(json!({ "op": "remove", "path": format!("{}/{}", path, pointer_seg(k)) }));
+            }
+            for (k, v) in y {
+                let p = format!("{}/{}", path, pointer_seg(k));
+                match x.get(k) {
+                    Some(old) => diff_values(old, v, &p, ops),
+                    None => ops.push(json!({ "op": "add", "path": p, "value": v })),
+                }
+            }
+        }
+        (Value::Array(x), Value::Array(y)) if x.len() == y.len() => {
+            for (i, (old, v)) in x.iter().zip(y).enumerate() { diff_values(old, v, &format!("{}/{}", path, i), ops); }
+        }
+        _ => ops.push(json!({ "op": "replace", "path": path, "value": b })),
+    }
+}
 
 // ── §2 HistoryManager ─────────────────────────────────────────────────────────
 
@@ -82,66 +181,258 @@ pub struct HistoryStats {
     pub can_undo: bool, pub can_redo: bool,
 }
 
+/// Snapshots kept by `HistoryManager::new`.
+const DEFAULT_MAX_HISTORY: usize = 50;
+
+/// Upper bound on retained redo branches — oldest trees are dropped first.
+const MAX_BRANCHES: usize = 32;
+
+/// A stashed redo future. `parent == None` forks off the live stack after
+/// `stack[fork]`; otherwise it forks after `frames[fork]` of branch `parent`.
+struct Branch { id: u32, parent: Option<u32>, fork: usize, frames: Vec<Frame> }
+
+#[derive(Serialize)]
+pub struct BranchInfo { pub id: u32, pub parent: Option<u32>, pub fork_index: usize, pub len: usize }
+
 #[wasm_bindgen]
 pub struct HistoryManager {
     stack:         VecDeque<Frame>,
     current_index: usize,
     max_history:   usize,
+    branching:     bool,
+    branches:      Vec<Branch>,
+    next_branch:   u32,
+    /// Byte cap on `get_memory_usage()`; 0 = count cap only.
+    max_bytes:     usize,
+    /// Snapshot codec — `None` = LZ4, else gzip level (0 = stored raw).
+    gzip:          Option<u32>,
+    diff:          bool,
+    /// `(group_key, last push ms, top hash)` of the last coalesced push.
+    coalesce:      Option<(String, f64, u64)>,
+    /// Stack index recorded by `mark_saved`; `None` once that snapshot is gone.
+    saved:         Option<usize>,
 }
 
 #[wasm_bindgen]
 impl HistoryManager {
     #[wasm_bindgen(constructor)]
-    pub fn new(initial: String) -> HistoryManager {
+    pub fn new(initial: String) -> HistoryManager { HistoryManager::new_with_capacity(initial, DEFAULT_MAX_HISTORY) }
+
+    /// Size the stack up front — large documents trade depth for memory.
+    /// A zero capacity is treated as 1.
+    pub fn new_with_capacity(initial: String, max_history: usize) -> HistoryManager {
         HistoryManager {
-            stack:         VecDeque::from([make_frame(&initial)]),
+            stack:         VecDeque::from([make_frame(&initial, String::new(), None)]),
             current_index: 0,
-            max_history:   80,
+            max_history:   max_history.max(1),
+            branching:     false,
+            branches:      Vec::new(),
+            next_branch:   0,
+            max_bytes:     0,
+            gzip:          None,
+            diff:          false,
+            coalesce:      None,
+            saved:         Some(0),
         }
     }
 
-    pub fn push_state(&mut self, state: String) {
+    /// Gzip snapshots at `level` (clamped to 0..=9) instead of LZ4. Lower is
+    /// faster per push; 0 stores states uncompressed — fastest push, most memory.
+    pub fn new_with_compression(initial: String, level: u32) -> HistoryManager {
+        let gzip = Some(level.min(9));
+        let stack = VecDeque::from([make_frame(&initial, String::new(), gzip)]);
+        HistoryManager { stack, gzip, ..HistoryManager::new(String::new()) }
+    }
+
+    /// Evict by memory rather than count: after every push the oldest
+    /// snapshots go until `get_memory_usage() <= max_bytes`. The current
+    /// state is always kept, even when it alone exceeds the budget.
+    pub fn new_with_byte_budget(initial: String, max_bytes: usize) -> HistoryManager {
+        HistoryManager { max_history: usize::MAX, max_bytes, ..HistoryManager::new(initial) }
+    }
+
+    /// Effective byte budget — `None` when only the entry count is capped.
+    pub fn byte_budget(&self) -> Option<usize> { Some(self.max_bytes).filter(|&b| b > 0) }
+
+    /// Store splice deltas against the previous snapshot instead of whole
+    /// frames, with a full keyframe every 10th entry. `undo`/`redo` are unchanged.
+    pub fn new_with_diff(initial: String) -> HistoryManager {
+        HistoryManager { diff: true, ..HistoryManager::new(initial) }
+    }
+
+    pub fn push_state(&mut self, state: String) { self.push_state_labeled(state, String::new()); }
+
+    /// `push_state` with a name ("Move header", "Checkpoint") stored on the
+    /// snapshot. Pushing an unchanged state only relabels the current one.
+    pub fn push_state_labeled(&mut self, state: String, label: String) {
         let h = fnv1a(&state);
-        if self.stack.get(self.current_index).map_or(false, |f| f.hash == h) { return; }
-        self.stack.truncate(self.current_index + 1);
-        self.stack.push_back(make_frame(&state));
+        if let Some(f) = self.stack.get_mut(self.current_index).filter(|f| f.hash == h) {
+            if !label.is_empty() { f.label = label; }
+            return;
+        }
+        self.drop_future();
+        let frame = match self.delta_base() {
+            Some(base) => make_delta(&base, &state, label),
+            None       => make_frame(&state, label, self.gzip),
+        };
+        self.stack.push_back(frame);
         self.current_index += 1;
-        if self.stack.len() > self.max_history {
-            self.stack.pop_front();
+        self.trim();
+    }
+
+    /// Whole session — every frame, branch and label plus the cursor and
+    /// config — as a binary blob for IndexedDB. Restore with `deserialize`.
+    pub fn serialize(&self) -> Vec<u8> { self.to_bytes() }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<HistoryManager, JsValue> {
+        HistoryManager::from_bytes(bytes).map_err(|e| JsValue::from_str(&format!("[history] deserialize: {}", e)))
+    }
+
+    /// Slider-style pushes: if the top snapshot came from a push with the
+    /// same `group_key` no more than `window_ms` ago (`now_ms` supplied by JS),
+    /// it is replaced rather than stacked. After an undo the push always
+    /// lands as a new entry, truncating the redo future as usual.
+    pub fn push_state_coalesced(&mut self, state: String, group_key: String, now_ms: f64, window_ms: f64) {
+        let top = self.stack.len() - 1;
+        let merge = self.current_index == top && top > 0
+            && self.coalesce.as_ref().is_some_and(|(k, t, h)| {
+                *k == group_key && *h == self.stack[top].hash && now_ms - t >= 0.0 && now_ms - t <= window_ms
+            });
+        let label = if merge {
+            if self.saved == Some(top) { self.saved = None; }
             self.current_index -= 1;
+            self.stack.pop_back().map(|f| f.label).unwrap_or_default()
+        } else { String::new() };
+        self.push_state_labeled(state, label);
+        let h = self.stack[self.current_index].hash;
+        self.coalesce = Some((group_key, now_ms, h));
+    }
+
+    /// Non-linear undo. When on, pushing after an undo stashes the redo
+    /// future as a branch instead of discarding it. Turning it off drops
+    /// every stored branch. Linear mode is the default.
+    pub fn set_branching(&mut self, enabled: bool) {
+        self.branching = enabled;
+        if !enabled { self.branches.clear(); }
+    }
+
+    /// `[{ id, parent, fork_index, len }]` — `parent: null` branches fork off
+    /// the live stack after `fork_index`; nested ones fork inside `parent`.
+    pub fn branches(&self) -> Result<JsValue, JsValue> {
+        let list: Vec<BranchInfo> = self.branches.iter().map(|b| BranchInfo {
+            id: b.id, parent: b.parent, fork_index: b.fork, len: b.frames.len(),
+        }).collect();
+        Ok(serde_wasm_bindgen::to_value(&list)?)
+    }
+
+    /// Make branch `id` the live future and land on its first state. The
+    /// future it replaces is stashed as a new branch, so nothing is lost.
+    pub fn switch_branch(&mut self, id: u32) -> Option<String> {
+        let parent = self.branches.iter().find(|b| b.id == id)?.parent;
+        if let Some(pid) = parent { self.switch_branch(pid)?; }
+        let pos = self.branches.iter().position(|b| b.id == id)?;
+        let b = self.branches.remove(pos);
+        self.current_index = b.fork;
+        self.drop_future();
+        for nb in self.branches.iter_mut().filter(|nb| nb.parent == Some(b.id)) {
+            nb.parent = None; nb.fork += b.fork + 1;
         }
+        self.stack.extend(b.frames);
+        self.current_index = b.fork + 1;
+        self.trim();
+        self.state_at(self.current_index)
     }
 
     pub fn undo(&mut self) -> Option<String> {
         if self.current_index == 0 { return None; }
         self.current_index -= 1;
-        decompress_frame(&self.stack[self.current_index])
+        self.state_at(self.current_index)
     }
 
     pub fn redo(&mut self) -> Option<String> {
         if self.current_index >= self.stack.len() - 1 { return None; }
         self.current_index += 1;
-        decompress_frame(&self.stack[self.current_index])
+        self.state_at(self.current_index)
+    }
+
+    /// `undo` that returns an RFC 6902 JSON Patch from the current state to
+    /// the undo target instead of the whole target, so large documents can be
+    /// patched in place. `None` when there is nothing to undo.
+    pub fn undo_diff(&mut self) -> Option<String> {
+        if self.current_index == 0 { return None; }
+        self.step_diff(self.current_index - 1)
+    }
+
+    pub fn redo_diff(&mut self) -> Option<String> {
+        if self.current_index >= self.stack.len() - 1 { return None; }
+        self.step_diff(self.current_index + 1)
+    }
+
+    /// What `undo` would return, without moving the cursor — for hover previews.
+    pub fn peek_undo(&self) -> Option<String> {
+        if self.current_index == 0 { return None; }
+        self.state_at(self.current_index - 1)
+    }
+
+    pub fn peek_redo(&self) -> Option<String> {
+        if self.current_index >= self.stack.len() - 1 { return None; }
+        self.state_at(self.current_index + 1)
     }
 
     pub fn undo_steps(&mut self, steps: usize) -> Option<String> {
         self.current_index = self.current_index.saturating_sub(steps);
-        decompress_frame(&self.stack[self.current_index])
+        self.state_at(self.current_index)
     }
 
+    /// Move the cursor straight to snapshot `index`. Out of range → `None`,
+    /// cursor untouched.
+    pub fn jump_to(&mut self, index: usize) -> Option<String> {
+        if index >= self.stack.len() { return None; }
+        self.current_index = index;
+        self.state_at(index)
+    }
+
+    pub fn len(&self)           -> usize { self.stack.len() }
+    pub fn is_empty(&self)      -> bool  { self.stack.is_empty() }
+    pub fn current_index(&self) -> usize { self.current_index }
+
+    /// Label of live snapshot `index` — `None` if out of range or unlabeled.
+    pub fn get_label(&self, index: usize) -> Option<String> {
+        self.stack.get(index).map(|f| f.label.clone()).filter(|l| !l.is_empty())
+    }
+
+    pub fn current_label(&self) -> Option<String> { self.get_label(self.current_index) }
+
+    /// Record the current snapshot as the saved document. A new manager
+    /// starts saved at its initial state.
+    pub fn mark_saved(&mut self) { self.saved = Some(self.current_index); }
+
+    /// Whether the cursor is off the saved snapshot — undo / redo back onto it
+    /// clears the flag. Once the saved snapshot is evicted, truncated by a
+    /// push after undo, or replaced by a coalesced push, this stays true
+    /// until the next `mark_saved`.
+    pub fn is_dirty(&self) -> bool { self.saved != Some(self.current_index) }
+
     pub fn can_undo(&self)  -> bool { self.current_index > 0 }
     pub fn can_redo(&self)  -> bool { self.current_index < self.stack.len() - 1 }
-    pub fn get_memory_usage(&self) -> usize { self.stack.iter().map(|f| f.data.len()).sum() }
+    pub fn get_memory_usage(&self) -> usize {
+        self.stack.iter().chain(self.branches.iter().flat_map(|b| b.frames.iter()))
+            .map(|f| f.data.len()).sum()
+    }
 
+    /// Lowering the cap trims the oldest entries; the cursor moves with them.
     pub fn set_max_history(&mut self, n: usize) {
-        self.max_history = n.max(2);
-        while self.stack.len() > self.max_history {
-            self.stack.pop_front();
-            if self.current_index > 0 { self.current_index -= 1; }
-        }
+        self.max_history = n.max(1);
+        self.trim();
     }
 
-    pub fn clear_future(&mut self) { self.stack.truncate(self.current_index + 1); }
+    /// Discard the redo future — and any branches forking inside it.
+    pub fn clear_future(&mut self) {
+        self.stack.truncate(self.current_index + 1);
+        let dangling: Vec<u32> = self.branches.iter()
+            .filter(|b| b.parent.is_none() && b.fork > self.current_index).map(|b| b.id).collect();
+        for id in dangling { self.drop_branch_tree(id); }
+    }
 
     pub fn get_stats(&self) -> String {
         let m = self.get_memory_usage(); let c = self.stack.len();
@@ -153,6 +444,279 @@ impl HistoryManager {
     }
 }
 
+impl HistoryManager {
+    /// Move the cursor to `to` and return the patch from the old current state.
+    fn step_diff(&mut self, to: usize) -> Option<String> {
+        let from = self.state_at(self.current_index)?;
+        let target = self.state_at(to)?;
+        self.current_index = to;
+        Some(json_patch(&from, &target))
+    }
+
+    /// Rebuild snapshot `i` from its nearest keyframe.
+    fn state_at(&self, i: usize) -> Option<String> {
+        let key = (0..=i).rev().find(|&k| !is_delta(&self.stack[k]))?;
+        let mut s = decompress_frame(&self.stack[key])?;
+        for f in self.stack.range(key + 1..=i) { s = apply_delta(&s, f)?; }
+        Some(s)
+    }
+
+    /// Full state to diff the next push against — `None` when it must be a keyframe.
+    fn delta_base(&self) -> Option<String> {
+        if !self.diff { return None; }
+        let tip = self.stack.len() - 1;
+        let run = self.stack.iter().rev().take_while(|f| is_delta(f)).count();
+        if run + 1 >= KEYFRAME_EVERY { return None; }
+        self.state_at(tip)
+    }
+
+    /// Rewrite delta frame `i` as a self-contained keyframe before its base goes away.
+    fn make_key(&mut self, i: usize) {
+        if !self.stack.get(i).is_some_and(is_delta) { return; }
+        if let Some(s) = self.state_at(i) {
+            self.stack[i].data = compress_state(&s, self.gzip);
+        }
+    }
+
+    /// Remove everything after the cursor — stashed as a branch in branching mode.
+    fn drop_future(&mut self) {
+        let fork = self.current_index;
+        if fork + 1 >= self.stack.len() { return; }
+        if self.saved.is_some_and(|i| i > fork) { self.saved = None; }
+        if self.branching { self.make_key(fork + 1); }
+        let future: Vec<Frame> = self.stack.drain(fork + 1..).collect();
+        if !self.branching { return; }
+        let id = self.next_branch; self.next_branch += 1;
+        // Live-stack branches that forked inside the stashed future now hang off it.
+        for b in self.branches.iter_mut().filter(|b| b.parent.is_none() && b.fork > fork) {
+            b.parent = Some(id); b.fork -= fork + 1;
+        }
+        self.branches.push(Branch { id, parent: None, fork, frames: future });
+        while self.branches.len() > MAX_BRANCHES {
+            let oldest = self.branches.iter().filter(|b| b.parent.is_none()).map(|b| b.id).min();
+            match oldest { Some(old) => self.drop_branch_tree(old), None => break }
+        }
+    }
+
+    /// Enforce the count cap and, if set, the byte budget.
+    fn trim(&mut self) {
+        while self.stack.len() > self.max_history { self.evict_front(); }
+        if self.max_bytes == 0 { return; }
+        while self.current_index > 0 && self.get_memory_usage() > self.max_bytes { self.evict_front(); }
+    }
+
+    /// Pop the oldest snapshot; branches forking off it go with it.
+    fn evict_front(&mut self) {
+        self.make_key(1);
+        self.stack.pop_front();
+        if self.current_index > 0 { self.current_index -= 1; }
+        self.saved = self.saved.and_then(|i| i.checked_sub(1));
+        let orphaned: Vec<u32> = self.branches.iter()
+            .filter(|b| b.parent.is_none() && b.fork == 0).map(|b| b.id).collect();
+        for id in orphaned { self.drop_branch_tree(id); }
+        for b in self.branches.iter_mut().filter(|b| b.parent.is_none()) { b.fork -= 1; }
+    }
+
+    fn drop_branch_tree(&mut self, id: u32) {
+        let mut doomed = vec![id];
+        let mut i = 0;
+        while i < doomed.len() {
+            let cur = doomed[i];
+            doomed.extend(self.branches.iter().filter(|b| b.parent == Some(cur)).map(|b| b.id));
+            i += 1;
+        }
+        self.branches.retain(|b| !doomed.contains(&b.id));
+    }
+}
+
+// ── §2 HistoryManager — persistence ──────────────────────────────────────────
+//
+//  "VXH" v3 │ flags u8 (1 branching, 2 diff, high nibble gzip level + 1) │ current u32 │ max u32 │ next_branch u32
+//  max_bytes u32 (v2+; v1 blobs load with no byte budget)
+//  saved u32 (v3+, MAX = none; older blobs load with no save point)
+//  frames: count u32 + per frame [hash u64][len u32][data][len u32][label utf-8]
+//  branches: count u32 + per branch [id u32][parent u32, MAX = none][fork u32][frames]
+//  All integers little-endian; frame data keeps its codec byte untouched.
+
+const HISTORY_MAGIC: &[u8; 3] = b"VXH";
+const HISTORY_VERSION: u8 = 3;
+
+fn put_u32(o: &mut Vec<u8>, v: usize) { o.extend_from_slice(&(v as u32).to_le_bytes()); }
+
+fn put_frames<'a>(o: &mut Vec<u8>, frames: impl ExactSizeIterator<Item = &'a Frame>) {
+    put_u32(o, frames.len());
+    for f in frames {
+        o.extend_from_slice(&f.hash.to_le_bytes());
+        put_u32(o, f.data.len()); o.extend_from_slice(&f.data);
+        put_u32(o, f.label.len()); o.extend_from_slice(f.label.as_bytes());
+    }
+}
+
+struct ByteReader<'a> { b: &'a [u8], pos: usize }
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.b.len())
+            .ok_or_else(|| format!("truncated at byte {}", self.pos))?;
+        let s = &self.b[self.pos..end]; self.pos = end; Ok(s)
+    }
+    fn u32(&mut self) -> Result<usize, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap_or_default()) as usize)
+    }
+    fn frames(&mut self) -> Result<Vec<Frame>, String> {
+        let n = self.u32()?;
+        let mut v = Vec::with_capacity(n.min(self.b.len() / 16));
+        for _ in 0..n {
+            let hash = u64::from_le_bytes(self.take(8)?.try_into().unwrap_or_default());
+            let len = self.u32()?; let data = self.take(len)?.to_vec();
+            let len = self.u32()?;
+            let label = String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "label is not utf-8".to_string())?;
+            if data.is_empty() { return Err("empty frame".into()); }
+            v.push(Frame { data, hash, label });
+        }
+        Ok(v)
+    }
+}
+
+/// Branch tree sanity for `from_bytes`: unique ids below `next_branch`,
+/// every parent present, no parent cycles, and each fork point inside the
+/// stack / parent frames.
+fn check_branches(branches: &[Branch], stack_len: usize, next_branch: u32) -> Result<(), String> {
+    let mut by_id: HashMap<u32, &Branch> = HashMap::new();
+    for b in branches {
+        if by_id.insert(b.id, b).is_some() { return Err(format!("duplicate branch id {}", b.id)); }
+        // The next stashed future would otherwise reuse a live id.
+        if b.id >= next_branch { return Err(format!("branch id {} not below next id {}", b.id, next_branch)); }
+    }
+    for b in branches {
+        let base = match b.parent {
+            None => stack_len,
+            Some(pid) => by_id.get(&pid).ok_or_else(|| format!("branch {} has missing parent {}", b.id, pid))?.frames.len(),
+        };
+        if b.fork >= base { return Err(format!("branch {} forks out of range", b.id)); }
+        // Any chain longer than the branch count revisits a branch.
+        let mut cur = b.parent;
+        for _ in 0..=branches.len() {
+            let Some(pid) = cur else { break };
+            if pid == b.id { return Err(format!("branch {} is its own ancestor", b.id)); }
+            cur = by_id.get(&pid).and_then(|p| p.parent);
+        }
+        if cur.is_some() { return Err(format!("branch {} has a parent cycle", b.id)); }
+    }
+    Ok(())
+}
+
+/// Whether every frame in a stack / branch run decodes, deltas applied in
+/// order from the keyframe before them.
+fn frames_decode<'a>(frames: impl Iterator<Item = &'a Frame>) -> bool {
+    let mut cur: Option<String> = None;
+    for f in frames {
+        cur = if is_delta(f) { cur.and_then(|s| apply_delta(&s, f)) } else { decompress_frame(f) };
+        if cur.is_none() { return false; }
+    }
+    true
+}
+
+impl HistoryManager {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut o = HISTORY_MAGIC.to_vec(); o.push(HISTORY_VERSION);
+        let gz = self.gzip.map_or(0, |l| l as u8 + 1);
+        o.push(self.branching as u8 | (self.diff as u8) << 1 | gz << 4);
+        put_u32(&mut o, self.current_index); put_u32(&mut o, self.max_history); put_u32(&mut o, self.next_branch as usize);
+        put_u32(&mut o, self.max_bytes);
+        put_u32(&mut o, self.saved.unwrap_or(u32::MAX as usize));
+        put_frames(&mut o, self.stack.iter());
+        put_u32(&mut o, self.branches.len());
+        for b in &self.branches {
+            put_u32(&mut o, b.id as usize);
+            put_u32(&mut o, b.parent.map_or(u32::MAX as usize, |p| p as usize));
+            put_u32(&mut o, b.fork);
+            put_frames(&mut o, b.frames.iter());
+        }
+        o
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<HistoryManager, String> {
+        let mut r = ByteReader { b: bytes, pos: 0 };
+        if r.take(3)? != HISTORY_MAGIC { return Err("bad header".into()); }
+        let version = r.take(1)?[0];
+        if version == 0 || version > HISTORY_VERSION { return Err(format!("unsupported version {}", version)); }
+        let flags = r.take(1)?[0];
+        let current_index = r.u32()?; let max_history = r.u32()?; let next_branch = r.u32()? as u32;
+        let max_bytes = if version >= 2 { r.u32()? } else { 0 };
+        let saved = if version >= 3 { Some(r.u32()?).filter(|&i| i != u32::MAX as usize) } else { None };
+        let stack: VecDeque<Frame> = r.frames()?.into();
+        let mut branches = Vec::new();
+        for _ in 0..r.u32()? {
+            let id = r.u32()? as u32;
+            let parent = Some(r.u32()? as u32).filter(|&p| p != u32::MAX);
+            let fork = r.u32()?;
+            let frames = r.frames()?;
+            if frames.first().is_none_or(is_delta) { return Err(format!("branch {} has no keyframe", id)); }
+            branches.push(Branch { id, parent, fork, frames });
+        }
+        if r.pos != bytes.len() { return Err("trailing bytes".into()); }
+        if stack.front().is_none_or(is_delta) { return Err("history has no keyframe".into()); }
+        if current_index >= stack.len() { return Err("cursor out of range".into()); }
+        check_branches(&branches, stack.len(), next_branch)?;
+        // Decode everything now: a bad frame found later by undo / redo would
+        // leave the cursor on a state that can't be read.
+        if !frames_decode(stack.iter()) || !branches.iter().all(|b| frames_decode(b.frames.iter())) {
+            return Err("corrupt frame data".into());
+        }
+        let stack_len = stack.len();
+        Ok(HistoryManager {
+            stack, current_index, max_history: max_history.max(1),
+            branching: flags & 1 != 0, branches, next_branch, max_bytes,
+            diff: flags & 2 != 0, coalesce: None, saved: saved.filter(|&i| i < stack_len),
+            gzip: (flags >> 4).checked_sub(1).map(|l| (l as u32).min(9)),
+        })
+    }
+}
+
+// ── §2 minify_project_json ───────────────────────────────────────────────────
+
+/// Node-level fields whose value equals the editor's implicit default.
+const NODE_DEFAULTS: &[(&str, Value)] = &[("locked", Value::Bool(false)), ("hidden", Value::Bool(false))];
+/// `props` fields that are dropped only when empty / false.
+const PROP_DEFAULT_KEYS: &[&str] = &["className", "style", "breakpoints", "stackOnMobile"];
+
+fn is_empty_default(v: &Value) -> bool {
+    match v {
+        Value::Bool(b)   => !b,
+        Value::String(s) => s.is_empty(),
+        Value::Object(o) => o.is_empty(),
+        _ => false,
+    }
+}
+
+/// Re-serialize project JSON without whitespace before it hits compression.
+/// `strip_defaults` also drops `locked:false`, `hidden:false` and empty
+/// `props.className` / `style` / `breakpoints` / `stackOnMobile:false` —
+/// all of which the editor reads back as the same value when absent.
+#[wasm_bindgen]
+pub fn minify_project_json(state: String, strip_defaults: bool) -> Result<String, JsValue> {
+    let mut project: Value = serde_json::from_str(&state)
+        .map_err(|e| JsValue::from_str(&format!("[state] parse: {}", e)))?;
+    if strip_defaults { strip_project_defaults(&mut project); }
+    serde_json::to_string(&project).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn strip_project_defaults(project: &mut Value) {
+    let Some(nodes) = project.as_object_mut() else { return };
+    for node in nodes.values_mut() {
+        let Some(obj) = node.as_object_mut() else { continue };
+        for (k, d) in NODE_DEFAULTS {
+            if obj.get(*k) == Some(d) { obj.remove(*k); }
+        }
+        if let Some(props) = obj.get_mut("props").and_then(|p| p.as_object_mut()) {
+            for k in PROP_DEFAULT_KEYS {
+                if props.get(*k).is_some_and(is_empty_default) { props.remove(*k); }
+            }
+        }
+    }
+}
+
 // ── §8 TreeManager — shared helpers ──────────────────────────────────────────
 
 /// IDs that can never be deleted (structural roots).
@@ -316,3 +880,121 @@ pub fn compute_structural_key(project_json: String) -> Result<String, JsValue> {
     }
     Ok(parts.join("|"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minified_project_round_trips() {
+        let src = r#"{
+            "a": { "id": "a", "children": ["b"], "locked": false, "hidden": true,
+                   "props": { "className": "", "style": {}, "stackOnMobile": false, "label": "Hi" } },
+            "b": { "id": "b", "locked": true, "props": { "className": "p-4" } }
+        }"#;
+        let original: Value = serde_json::from_str(src).unwrap();
+        let plain = minify_project_json(src.into(), false).unwrap();
+        assert!(!plain.contains([' ', '\n']));
+        assert_eq!(serde_json::from_str::<Value>(&plain).unwrap(), original);
+
+        let stripped: Value = serde_json::from_str(&minify_project_json(src.into(), true).unwrap()).unwrap();
+        assert_eq!(stripped["a"], json!({ "id": "a", "children": ["b"], "hidden": true, "props": { "label": "Hi" } }));
+        assert_eq!(stripped["b"], original["b"]);
+    }
+
+    /// s0 → s1 → s3 live, with the abandoned `[s2]` future stashed as a root branch.
+    fn branched() -> HistoryManager {
+        let mut h = HistoryManager::new("s0".into());
+        h.set_branching(true);
+        h.push_state("s1".into());
+        h.push_state("s2".into());
+        h.undo();
+        h.push_state("s3".into());
+        h
+    }
+
+    fn branch(id: u32, parent: Option<u32>, fork: usize) -> Branch {
+        Branch { id, parent, fork, frames: vec![make_frame("b", String::new(), None)] }
+    }
+
+    #[test]
+    fn history_blob_round_trips() {
+        let mut h = branched();
+        h.set_max_history(20);
+        let mut back = HistoryManager::from_bytes(&h.to_bytes()).unwrap();
+        assert_eq!(back.to_bytes(), h.to_bytes());
+        assert_eq!((back.len(), back.current_index(), back.max_history, back.branches.len()), (3, 2, 20, 1));
+        assert_eq!(back.undo().as_deref(), Some("s1"));
+        assert_eq!(back.switch_branch(back.branches[0].id).as_deref(), Some("s2"));
+        let blob = h.to_bytes();
+        assert!(HistoryManager::from_bytes(&blob[..blob.len() - 1]).is_err());
+        assert!(HistoryManager::from_bytes(b"nope").is_err());
+    }
+
+    #[test]
+    fn history_blob_rejects_bad_branch_trees() {
+        let reject = |edit: &dyn Fn(&mut HistoryManager), want: &str| {
+            let mut h = branched();
+            h.next_branch = 100;
+            edit(&mut h);
+            let err = HistoryManager::from_bytes(&h.to_bytes()).err().unwrap();
+            assert!(err.contains(want), "{}: {}", want, err);
+        };
+        reject(&|h| h.branches[0].fork = h.stack.len(), "forks out of range");
+        reject(&|h| { let id = h.branches[0].id; h.branches.push(branch(40, Some(id), 1)); }, "branch 40 forks out of range");
+        reject(&|h| h.branches.push(branch(41, Some(99), 0)), "missing parent 99");
+        reject(&|h| { h.branches.push(branch(42, Some(43), 0)); h.branches.push(branch(43, Some(42), 0)); }, "its own ancestor");
+        reject(&|h| { h.branches.insert(0, branch(45, Some(46), 0)); h.branches.push(branch(46, Some(47), 0)); h.branches.push(branch(47, Some(46), 0)); },
+               "branch 45 has a parent cycle");
+        reject(&|h| { let id = h.branches[0].id; h.branches.push(branch(id, None, 0)); }, "duplicate branch id");
+        reject(&|h| { h.branches.push(branch(48, None, 0)); h.next_branch = 48; }, "branch id 48 not below next id 48");
+        // Frames are decoded up front, not just the one under the cursor.
+        reject(&|h| h.stack[0].data.truncate(3), "corrupt frame data");
+        reject(&|h| h.branches[0].frames[0].data.truncate(3), "corrupt frame data");
+
+        // A valid nested branch still loads.
+        let mut h = branched();
+        h.next_branch = 100;
+        let id = h.branches[0].id;
+        h.branches.push(branch(44, Some(id), 0));
+        assert_eq!(HistoryManager::from_bytes(&h.to_bytes()).unwrap().branches.len(), 2);
+    }
+
+    #[test]
+    fn capacity_caps_and_trims_oldest() {
+        let mut h = HistoryManager::new("s0".into());
+        for i in 1..=60 { h.push_state(format!("s{}", i)); }
+        assert_eq!((h.len(), h.current_index()), (50, 49));
+        assert_eq!(h.jump_to(0).as_deref(), Some("s11"));
+
+        let mut h = HistoryManager::new_with_capacity("s0".into(), 0);
+        h.push_state("s1".into());
+        assert_eq!((h.len(), h.max_history), (1, 1));
+
+        let mut h = HistoryManager::new_with_capacity("s0".into(), 10);
+        for i in 1..=9 { h.push_state(format!("s{}", i)); }
+        h.undo_steps(2);
+        h.set_max_history(4);
+        assert_eq!((h.len(), h.current_index()), (4, 1));
+        assert_eq!(h.jump_to(0).as_deref(), Some("s6"));
+        assert_eq!(h.jump_to(3).as_deref(), Some("s9"));
+    }
+
+    #[test]
+    fn compression_level_applies_to_every_push() {
+        let big = "{\"a\":1}".repeat(500);
+        let mut raw = HistoryManager::new_with_compression("{}".into(), 0);
+        let mut gz = HistoryManager::new_with_compression("{}".into(), 42);
+        assert_eq!((raw.gzip, gz.gzip), (Some(0), Some(9)));
+        raw.push_state(big.clone());
+        gz.push_state(big.clone());
+        assert_eq!(raw.stack[1].data[0], CODEC_RAW);
+        assert_eq!(gz.stack[1].data[0], CODEC_GZIP);
+        assert_eq!(raw.stack[1].data.len(), big.len() + 1);
+        assert!(gz.stack[1].data.len() < big.len() / 10);
+        for h in [&mut raw, &mut gz] {
+            assert_eq!(h.undo().as_deref(), Some("{}"));
+            assert_eq!(h.redo().as_deref(), Some(big.as_str()));
+        }
+    }
+}
@@ -3,11 +3,14 @@
 // ══════════════════════════════════════════════════════════════════════════════
 //
 //  §4  ColorEngine — HSL/RGB/Hex transforms, WCAG contrast, palettes
+//      contrast_ratio / passes_wcag — hex or rgb() pairs, AA / AAA checks
+//      extract_palette — median-cut dominant colors of an RGBA buffer
 //  §5  TailwindOptimizer — deduplicate_classes, sort_tailwind_classes
 //  §11 CSSGenerator — build_breakpoint_css, build_mobile_css, serialize_style_object
 
 use std::collections::{HashMap, HashSet};
 use wasm_bindgen::prelude::*;
+use serde::Serialize;
 use serde_json::Value;
 
 // ── §4 Color helpers (pub(crate) so figma.rs can use them) ───────────────────
@@ -58,7 +61,7 @@ fn rel_lum(r: u8, g: u8, b: u8) -> f64 {
 
 // ── §4 ColorEngine ────────────────────────────────────────────────────────────
 
-#[wasm_bindgen] pub struct ColorEngine;
+#[wasm_bindgen] #[derive(Default)] pub struct ColorEngine;
 
 #[wasm_bindgen]
 impl ColorEngine {
@@ -115,7 +118,7 @@ impl ColorEngine {
     }
 
     pub fn generate_scale(&self, hex: String, steps: u32) -> Result<String, JsValue> {
-        let steps = steps.max(3).min(20) as usize;
+        let steps = steps.clamp(3, 20) as usize;
         let (r,g,b) = parse_hex(&hex)?;
         let (h,s,_) = rgb_to_hsl(r,g,b);
         let sc: Vec<String> = (0..steps).map(|i| {
@@ -135,6 +138,106 @@ impl ColorEngine {
     }
 }
 
+/// `#rgb`, `#rrggbb` or `rgb(r, g, b)` (commas or spaces; channels 0–255 or
+/// percentages) → RGB. Any alpha channel makes it an error, since contrast
+/// over an unknown backdrop is undefined.
+pub(crate) fn parse_css_rgb(c: &str) -> Result<(u8, u8, u8), String> {
+    let bad = || format!("[color] unparseable color: {}", c);
+    let t = c.trim().to_ascii_lowercase();
+    if let Some(h) = t.strip_prefix('#') {
+        if !(h.len() == 3 || h.len() == 6) || !h.chars().all(|ch| ch.is_ascii_hexdigit()) { return Err(bad()); }
+        return parse_hex(h).map_err(|_| bad());
+    }
+    let inner = t.strip_prefix("rgb(").and_then(|r| r.strip_suffix(')')).ok_or_else(bad)?;
+    let parts: Vec<&str> = inner.split(|ch: char| ch == ',' || ch.is_whitespace()).filter(|p| !p.is_empty()).collect();
+    let [r, g, b] = parts[..] else { return Err(bad()) };
+    let chan = |p: &str| -> Option<u8> {
+        let v = match p.strip_suffix('%') { Some(pc) => pc.parse::<f64>().ok()? / 100.0 * 255.0, None => p.parse::<f64>().ok()? };
+        (0.0..=255.0).contains(&v).then(|| v.round() as u8)
+    };
+    Ok((chan(r).ok_or_else(bad)?, chan(g).ok_or_else(bad)?, chan(b).ok_or_else(bad)?))
+}
+
+pub(crate) fn wcag_ratio(fg: &str, bg: &str) -> Result<f64, String> {
+    let ((r1, g1, b1), (r2, g2, b2)) = (parse_css_rgb(fg)?, parse_css_rgb(bg)?);
+    let (l1, l2) = (rel_lum(r1, g1, b1), rel_lum(r2, g2, b2));
+    Ok((l1.max(l2) + 0.05) / (l1.min(l2) + 0.05))
+}
+
+/// WCAG 2 contrast ratio (1–21) between two CSS colors; see `parse_css_rgb`
+/// for the accepted forms. Unlike `ColorEngine::get_contrast_ratio` this also
+/// takes `rgb(…)`.
+#[wasm_bindgen]
+pub fn contrast_ratio(fg: String, bg: String) -> Result<f64, JsValue> {
+    wcag_ratio(&fg, &bg).map_err(|e| JsValue::from_str(&e))
+}
+
+/// `level` is `"AA"` (4.5, or 3 for large text) or `"AAA"` (7, or 4.5).
+/// Unparseable colors or an unknown level count as failing.
+#[wasm_bindgen]
+pub fn passes_wcag(fg: String, bg: String, level: String, large_text: bool) -> bool {
+    let min = match (level.trim().to_ascii_uppercase().as_str(), large_text) {
+        ("AA", false) => 4.5, ("AA", true) => 3.0,
+        ("AAA", false) => 7.0, ("AAA", true) => 4.5,
+        _ => return false,
+    };
+    wcag_ratio(&fg, &bg).is_ok_and(|r| r >= min)
+}
+
+/// Pixels clustered at most; larger images are sampled on a regular grid.
+const PALETTE_SAMPLES: usize = 16_384;
+
+#[derive(Serialize)]
+pub struct PaletteColor { pub hex: String, pub coverage: f64 }
+
+/// Dominant colors of an RGBA buffer via median cut (each box split at the
+/// midpoint of its widest channel): up to `k` (1–32) hex
+/// colors with their share of the sampled opaque pixels, most common first.
+/// Pixels under 50% alpha are ignored; an all-transparent image yields `[]`.
+#[wasm_bindgen]
+pub fn extract_palette(rgba: &[u8], width: u32, height: u32, k: usize) -> Result<JsValue, JsValue> {
+    let pal = palette(rgba, width, height, k).map_err(|e| JsValue::from_str(&e))?;
+    Ok(serde_wasm_bindgen::to_value(&pal)?)
+}
+
+pub(crate) fn palette(rgba: &[u8], width: u32, height: u32, k: usize) -> Result<Vec<PaletteColor>, String> {
+    let (w, h) = (width as usize, height as usize);
+    let expected = w.checked_mul(h).and_then(|n| n.checked_mul(4));
+    if expected != Some(rgba.len()) {
+        return Err(format!("[palette] buffer is {} bytes, expected {}x{}x4", rgba.len(), width, height));
+    }
+    let step = ((w * h) as f64 / PALETTE_SAMPLES as f64).sqrt().ceil().max(1.0) as usize;
+    let mut px: Vec<[u8; 3]> = Vec::new();
+    for y in (0..h).step_by(step) {
+        for x in (0..w).step_by(step) {
+            let i = (y * w + x) * 4;
+            if rgba[i + 3] >= 128 { px.push([rgba[i], rgba[i + 1], rgba[i + 2]]); }
+        }
+    }
+    let total = px.len();
+    if total == 0 { return Ok(Vec::new()); }
+    // (channel range, channel, midpoint) of a box's widest channel
+    let spread = |b: &[[u8; 3]]| (0..3).map(|c| {
+        let (lo, hi) = b.iter().fold((255u8, 0u8), |(lo, hi), p| (lo.min(p[c]), hi.max(p[c])));
+        (hi - lo, c, lo + (hi - lo) / 2)
+    }).max().unwrap_or((0, 0, 0));
+    let mut boxes = vec![px];
+    while boxes.len() < k.clamp(1, 32) {
+        // split the box with the widest channel range at that range's midpoint,
+        // so runs of one exact color never straddle two boxes
+        let Some((i, (_, c, mid))) = boxes.iter().enumerate().map(|(i, b)| (i, spread(b)))
+            .filter(|(_, (r, _, _))| *r > 0).max_by_key(|(_, s)| *s) else { break };
+        let (lower, upper): (Vec<_>, Vec<_>) = boxes.swap_remove(i).into_iter().partition(|p| p[c] <= mid);
+        boxes.push(lower); boxes.push(upper);
+    }
+    let mut out: Vec<PaletteColor> = boxes.iter().map(|b| {
+        let avg = |c: usize| (b.iter().map(|p| p[c] as u64).sum::<u64>() as f64 / b.len() as f64).round() as u8;
+        PaletteColor { hex: format!("#{:02x}{:02x}{:02x}", avg(0), avg(1), avg(2)), coverage: b.len() as f64 / total as f64 }
+    }).collect();
+    out.sort_by(|a, b| b.coverage.partial_cmp(&a.coverage).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(out)
+}
+
 // ── §5 TailwindOptimizer ──────────────────────────────────────────────────────
 
 const CONFLICT_PREFIXES: &[&str] = &[
@@ -192,7 +295,8 @@ pub fn sort_tailwind_classes(classes: String) -> String {
         if b.starts_with("gap-") { return 42; }
         if b.starts_with("text-")||b.starts_with("font-")||b.starts_with("leading-")||b.starts_with("tracking-") { return 50; }
         if b.starts_with("bg-")||b.starts_with("from-")||b.starts_with("via-")||b.starts_with("to-") { return 60; }
-        if b.starts_with("border") { return 70; } if b.starts_with("rounded") { return 71; }
+        if b.starts_with("border") { return 70; }
+        if b.starts_with("rounded") { return 71; }
         if b.starts_with("shadow")||b.starts_with("ring") { return 80; }
         if b.starts_with("opacity-")||b.starts_with("blur") { return 81; }
         if b.starts_with("transition")||b.starts_with("duration-")||b.starts_with("ease-") { return 90; }
@@ -281,14 +385,68 @@ pub fn build_mobile_css(has_mobile_nodes: bool) -> String {
 /// Mirrors: `codeGenerator.serializeStyle(styleObj)`
 #[wasm_bindgen]
 pub fn serialize_style_object(style_json: String) -> Result<String, JsValue> {
+    let obj: serde_json::Map<String, Value> = serde_json::from_str(&style_json)
+        .map_err(|e| JsValue::from_str(&format!("[css] parse style: {}", e)))?;
+    Ok(style_decls(&obj).join("; "))
+}
+
+/// Physical → logical property names (camelCase) for RTL-ready output.
+const LOGICAL_PROPS: &[(&str, &str)] = &[
+    ("marginLeft", "marginInlineStart"), ("marginRight", "marginInlineEnd"),
+    ("paddingLeft", "paddingInlineStart"), ("paddingRight", "paddingInlineEnd"),
+    ("left", "insetInlineStart"), ("right", "insetInlineEnd"),
+    ("borderLeft", "borderInlineStart"), ("borderRight", "borderInlineEnd"),
+    ("borderLeftWidth", "borderInlineStartWidth"), ("borderRightWidth", "borderInlineEndWidth"),
+    ("borderLeftColor", "borderInlineStartColor"), ("borderRightColor", "borderInlineEndColor"),
+    ("borderLeftStyle", "borderInlineStartStyle"), ("borderRightStyle", "borderInlineEndStyle"),
+    ("borderTopLeftRadius", "borderStartStartRadius"), ("borderTopRightRadius", "borderStartEndRadius"),
+    ("borderBottomLeftRadius", "borderEndStartRadius"), ("borderBottomRightRadius", "borderEndEndRadius"),
+];
+
+/// Rewrite a style object with logical properties (`marginLeft` →
+/// `marginInlineStart`, `textAlign: left` → `start`, `float: left` → `inline-start`).
+pub(crate) fn logical_style(st: &serde_json::Map<String, Value>) -> serde_json::Map<String, Value> {
+    st.iter().map(|(k, v)| {
+        let key = LOGICAL_PROPS.iter().find(|(p, _)| p == k).map_or(k.as_str(), |(_, l)| l).to_string();
+        let val = match (k.as_str(), v.as_str()) {
+            ("textAlign", Some("left")) => Value::from("start"),
+            ("textAlign", Some("right")) => Value::from("end"),
+            ("float"|"clear", Some("left")) => Value::from("inline-start"),
+            ("float"|"clear", Some("right")) => Value::from("inline-end"),
+            _ => v.clone(),
+        };
+        (key, val)
+    }).collect()
+}
+
+/// Tailwind physical utilities → logical ones (`ml-4` → `ms-4`, `text-left` →
+/// `text-start`), keeping variant prefixes and the negative sign.
+pub(crate) fn logical_class(c: &str) -> String {
+    const MAP: &[(&str, &str)] = &[
+        ("ml-", "ms-"), ("mr-", "me-"), ("pl-", "ps-"), ("pr-", "pe-"),
+        ("left-", "start-"), ("right-", "end-"),
+        ("rounded-l", "rounded-s"), ("rounded-r", "rounded-e"),
+        ("border-l", "border-s"), ("border-r", "border-e"),
+        ("text-left", "text-start"), ("text-right", "text-end"),
+        ("float-left", "float-start"), ("float-right", "float-end"),
+    ];
+    let (variants, base) = c.rsplit_once(':').map_or(("", c), |(v, b)| (v, b));
+    let (neg, util) = base.strip_prefix('-').map_or(("", base), |u| ("-", u));
+    let Some((from, to)) = MAP.iter().find(|(f, _)| {
+        util.starts_with(f) && (f.ends_with('-') || util.len() == f.len() || util[f.len()..].starts_with('-'))
+    }) else { return c.to_string() };
+    let sep = if variants.is_empty() { "" } else { ":" };
+    format!("{}{}{}{}{}", variants, sep, neg, to, &util[from.len()..])
+}
+
+/// Style object → `"prop: value"` declarations; numbers get `px` unless unitless or 0.
+pub(crate) fn style_decls(obj: &serde_json::Map<String, Value>) -> Vec<String> {
     const UNITLESS: &[&str] = &[
         "fontWeight","opacity","zIndex","flexGrow","flexShrink",
         "order","scale","lineHeight","aspectRatio","columns",
     ];
-    let obj: serde_json::Map<String, Value> = serde_json::from_str(&style_json)
-        .map_err(|e| JsValue::from_str(&format!("[css] parse style: {}", e)))?;
     let mut parts: Vec<String> = Vec::new();
-    for (k, v) in &obj {
+    for (k, v) in obj {
         let css_prop = camel_to_kebab(k);
         let css_val = match v {
             Value::Number(n) => {
@@ -301,5 +459,28 @@ pub fn serialize_style_object(style_json: String) -> Result<String, JsValue> {
         };
         parts.push(format!("{}: {}", css_prop, css_val));
     }
-    Ok(parts.join("; "))
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn logical_properties_for_rtl() {
+        let st = json!({ "marginLeft": 8, "paddingRight": "4px", "textAlign": "left", "float": "right", "color": "red" });
+        let out = Value::Object(logical_style(st.as_object().unwrap()));
+        assert_eq!(out, json!({ "marginInlineStart": 8, "paddingInlineEnd": "4px", "textAlign": "start", "float": "inline-end", "color": "red" }));
+    }
+
+    #[test]
+    fn logical_tailwind_utilities() {
+        for (from, to) in [("ml-4", "ms-4"), ("hover:-ml-2", "hover:-ms-2"), ("md:pr-6", "md:pe-6"), ("text-left", "text-start"),
+                           ("rounded-l", "rounded-s"), ("rounded-r-md", "rounded-e-md"), ("border-l-2", "border-s-2")] {
+            assert_eq!(logical_class(from), to);
+        }
+        // Prefix look-alikes stay put.
+        for c in ["rounded-lg", "border-lime-500", "text-lg", "mx-4"] { assert_eq!(logical_class(c), c); }
+    }
 }
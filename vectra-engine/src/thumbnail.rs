@@ -34,7 +34,8 @@ fn thumb_color(node_type: &str) -> &'static str {
     }
 }
 
-fn px_val(v: Option<&Value>) -> f64 {
+/// Numeric or "12px" style value → f64 (0 when absent/unparseable).
+pub(crate) fn px_val(v: Option<&Value>) -> f64 {
     v.and_then(|x| x.as_f64())
      .or_else(|| v.and_then(|x| x.as_str())
          .and_then(|s| s.trim_end_matches("px").parse().ok()))
@@ -116,8 +117,8 @@ pub fn generate_thumbnail(project_json: String, pages_json: String) -> String {
         let raw_h = px_val(style.and_then(|s| s.get("height")))
             .max(px_val(style.and_then(|s| s.get("minHeight")))).max(1.0);
 
-        let cx = (raw_x * scale).round().max(0.0).min(THUMB_W - 2.0);
-        let cy = (raw_y * scale + offset_y).round().max(0.0).min(THUMB_H - 1.0);
+        let cx = (raw_x * scale).round().clamp(0.0, THUMB_W - 2.0);
+        let cy = (raw_y * scale + offset_y).round().clamp(0.0, THUMB_H - 1.0);
         let cw = (raw_w * scale).round().max(2.0).min(THUMB_W - cx);
         let ch = (raw_h * scale).round().max(1.0).min(THUMB_H - cy);
         if cw <= 0.0 || ch <= 0.0 { continue; }
@@ -125,13 +126,13 @@ pub fn generate_thumbnail(project_json: String, pages_json: String) -> String {
         let fill = thumb_color(node.get("type").and_then(|t| t.as_str()).unwrap_or("_default"));
 
         if ch < 6.0 {
-            let _ = write!(rects_svg,
-                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" opacity=\"0.6\"/>\n",
+            let _ = writeln!(rects_svg,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" opacity=\"0.6\"/>",
                 cx as i32, cy as i32, cw as i32, ch.max(2.0) as i32, fill);
         } else {
             let rx = (cw / 8.0).floor().min(2.0) as i32;
-            let _ = write!(rects_svg,
-                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" rx=\"{}\" opacity=\"0.75\"/>\n",
+            let _ = writeln!(rects_svg,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" rx=\"{}\" opacity=\"0.75\"/>",
                 cx as i32, cy as i32, cw as i32, ch as i32, fill, rx);
         }
     }